@@ -0,0 +1,51 @@
+//! Compares [`PathBufD::push_str`] against [`PathBufD::push`] for short,
+//! separator-free components.
+//!
+//! Run with `cargo bench --bench push_str`.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use pathbufd::PathBufD;
+
+const COMPONENTS: [&str; 8] = [
+    "usr", "local", "share", "doc", "pathbufd", "examples", "a", "b.txt",
+];
+const ITERATIONS: u32 = 200_000;
+
+fn run<F>(name: &str, mut f: F) -> Duration
+where
+    F: FnMut(&mut PathBufD, &str),
+{
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        let mut buf = PathBufD::with_capacity(64);
+
+        for component in COMPONENTS {
+            f(&mut buf, black_box(component));
+        }
+
+        black_box(buf);
+    }
+
+    let elapsed = start.elapsed();
+    println!(
+        "{name:<10} {:>8.1} ns/iter",
+        elapsed.as_nanos() as f64 / f64::from(ITERATIONS)
+    );
+
+    elapsed
+}
+
+fn main() {
+    // warm up
+    run("warmup", |buf, s| buf.push(s));
+
+    let push = run("push", |buf, s| buf.push(s));
+    let push_str = run("push_str", |buf, s| buf.push_str(s));
+
+    println!(
+        "push_str / push: {:.2}",
+        push_str.as_secs_f64() / push.as_secs_f64()
+    );
+}
@@ -1,14 +1,28 @@
+use std::borrow::Cow;
 use std::collections::TryReserveError;
-use std::env::current_dir;
+use std::env::{self, current_dir, JoinPathsError};
 use std::ffi::{OsStr, OsString};
-use std::fmt::{Arguments, Display};
-use std::path::{Path, PathBuf};
-use std::cmp::Eq;
+use std::fmt::{Arguments, Debug, Display};
+use std::hash::{BuildHasher, DefaultHasher, Hash, Hasher, RandomState};
+use std::fs::{self, File, FileTimes, OpenOptions, ReadDir};
+use std::io::{self, BufRead, BufReader};
+use std::path::{self, Component, Components, Path, PathBuf, MAIN_SEPARATOR_STR};
+use std::cmp::{Eq, Ordering};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
 
 use serde::{Deserialize, Serialize};
 
 /// [`PathBuf`] wrapper
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Default)]
 pub struct PathBufD(PathBuf);
 
 impl PathBufD {
@@ -24,7 +38,7 @@ impl PathBufD {
 
     /// Creates a new [`PathBufD`] in the current directory
     pub fn current() -> Self {
-        Self(current_dir().unwrap_or(PathBuf::new()))
+        Self(current_dir().unwrap_or_default())
     }
 
     /// Coerces to a [`Path`] slice.
@@ -32,13 +46,28 @@ impl PathBufD {
         self.0.as_path()
     }
 
+    /// Gets a reference to the underlying [`PathBuf`].
+    pub fn inner(&self) -> &PathBuf {
+        &self.0
+    }
+
+    /// Gets a mutable reference to the underlying [`PathBuf`].
+    pub fn inner_mut(&mut self) -> &mut PathBuf {
+        &mut self.0
+    }
+
+    /// Consumes the [`PathBufD`], yielding the underlying [`PathBuf`].
+    pub fn into_inner(self) -> PathBuf {
+        self.0
+    }
+
     /// Gets `Vec<u8>` representation of the inner string.
     pub fn as_bytes(&self) -> Vec<u8> {
         self.to_string().as_bytes().to_owned()
     }
 
     /// Extends self with path.
-    pub fn push<P>(&mut self, path: P) -> ()
+    pub fn push<P>(&mut self, path: P)
     where
         P: AsRef<Path>,
     {
@@ -106,14 +135,14 @@ impl PathBufD {
     /// Invokes [`clear`] on the underlying instance of [`OsString`].
     ///
     /// [`clear`]: OsString::clear
-    pub fn clear(&mut self) -> () {
+    pub fn clear(&mut self) {
         self.0.clear()
     }
 
     /// Invokes [`reserve`] on the underlying instance of [`OsString`].
     ///
     /// [`reserve`]: OsString::reserve
-    pub fn reserve(&mut self, additional: usize) -> () {
+    pub fn reserve(&mut self, additional: usize) {
         self.0.reserve(additional)
     }
 
@@ -127,7 +156,7 @@ impl PathBufD {
     /// Invokes [`reserve_exact`] on the underlying instance of [`OsString`].
     ///
     /// [`reserve_exact`]: OsString::reserve_exact
-    pub fn reserve_exact(&mut self, additional: usize) -> () {
+    pub fn reserve_exact(&mut self, additional: usize) {
         self.0.reserve_exact(additional)
     }
 
@@ -148,7 +177,7 @@ impl PathBufD {
     /// Invokes [`shrink_to`] on the underlying instance of [`OsString`].
     ///
     /// [`shrink_to`]: OsString::shrink_to
-    pub fn shrink_to(&mut self, min_capacity: usize) -> () {
+    pub fn shrink_to(&mut self, min_capacity: usize) {
         self.0.shrink_to(min_capacity)
     }
 
@@ -165,63 +194,6501 @@ impl PathBufD {
 
         buf
     }
-}
 
-impl Default for PathBufD {
-    fn default() -> Self {
-        Self(PathBuf::default())
+    /// Collects each component of `self` into a [`String`].
+    ///
+    /// Components which are not valid UTF-8 are converted lossily (see
+    /// [`OsStr::to_string_lossy`]), so the result may not round-trip through
+    /// [`PathBufD::from_component_vec`] for such paths.
+    pub fn to_component_vec(&self) -> Vec<String> {
+        self.0
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect()
     }
-}
 
-impl Display for PathBufD {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0.to_str().unwrap_or(""))
+    /// Creates a new [`PathBufD`] by pushing every part of `parts` in order.
+    ///
+    /// This is the inverse of [`PathBufD::to_component_vec`] for paths which are
+    /// valid UTF-8.
+    pub fn from_component_vec<S>(parts: &[S]) -> Self
+    where
+        S: AsRef<str>,
+    {
+        let mut buf = Self::new();
+
+        for part in parts {
+            buf.push(part.as_ref())
+        }
+
+        buf
     }
-}
 
-impl AsRef<Path> for PathBufD {
-    fn as_ref(&self) -> &Path {
-        self.as_path()
+    /// Compares `self` and `other` component by component using natural
+    /// ordering, so runs of digits are compared by their numeric value
+    /// (`file2` sorts before `file10`).
+    pub fn natural_cmp(&self, other: &PathBufD) -> Ordering {
+        let mut a = self.0.components();
+        let mut b = other.0.components();
+
+        loop {
+            match (a.next(), b.next()) {
+                (Some(x), Some(y)) => {
+                    let ordering = natural_cmp_str(
+                        &x.as_os_str().to_string_lossy(),
+                        &y.as_os_str().to_string_lossy(),
+                    );
+
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+                (Some(_), None) => return Ordering::Greater,
+                (None, Some(_)) => return Ordering::Less,
+                (None, None) => return Ordering::Equal,
+            }
+        }
     }
-}
 
-impl Into<PathBufD> for PathBuf {
-    fn into(self) -> PathBufD {
-        PathBufD(self)
+    /// Creates a new [`PathBufD`] from `s`, rejecting input which is not valid UTF-8.
+    ///
+    /// A path created this way is always losslessly displayable.
+    pub fn from_utf8_checked<S>(s: S) -> Result<Self, PathError>
+    where
+        S: Into<OsString>,
+    {
+        let s = s.into();
+
+        if s.to_str().is_none() {
+            return Err(PathError::NotUtf8);
+        }
+
+        Ok(Self(PathBuf::from(s)))
     }
-}
 
-impl From<PathBufD> for PathBuf {
-    fn from(value: PathBufD) -> Self {
-        value.0
+    /// Simplifies a verbatim (`\\?\C:\...`) path into its most compatible form.
+    ///
+    /// The verbatim prefix is only stripped when the result stays within the
+    /// legacy `MAX_PATH` limit and contains no reserved or otherwise invalid
+    /// names, matching the behavior of the `dunce` crate. On platforms other
+    /// than Windows this is a cheap clone.
+    pub fn simplified(&self) -> Self {
+        #[cfg(windows)]
+        {
+            if is_safe_to_strip_verbatim(&self.0) {
+                if let Some(stripped) = self.0.to_str().and_then(|s| s.strip_prefix(r"\\?\")) {
+                    return Self(PathBuf::from(stripped));
+                }
+            }
+        }
+
+        self.clone()
     }
-}
 
-// macro
-/// Format [`Arguments`] into a [`PathBufD`]
-pub fn pathbufd_fmt(args: Arguments) -> PathBufD {
-    let string = if let Some(s) = args.as_str() {
-        s
-    } else {
-        &args.to_string()
-    };
+    /// Renders `self` as a [`String`], separating components with `sep`.
+    ///
+    /// A root component is rendered as a leading `sep` (so `/a/b` with `:`
+    /// becomes `:a:b`), and a Windows prefix (`C:`) is kept as-is. Components
+    /// which are not valid UTF-8 are converted lossily.
+    pub fn render_with_separator(&self, sep: char) -> String {
+        let mut out = String::new();
+        let mut needs_separator = false;
 
-    let mut pathbufd = PathBufD::new();
-    for split in string.split("/") {
-        if split.is_empty() {
-            pathbufd.push("/");
-            continue;
+        for component in self.0.components() {
+            match component {
+                Component::Prefix(prefix) => {
+                    out.push_str(&prefix.as_os_str().to_string_lossy());
+                }
+                Component::RootDir => {
+                    out.push(sep);
+                    needs_separator = false;
+                }
+                _ => {
+                    if needs_separator {
+                        out.push(sep);
+                    }
+
+                    out.push_str(&component.as_os_str().to_string_lossy());
+                    needs_separator = true;
+                }
+            }
         }
 
-        pathbufd.push(split);
+        out
     }
 
-    return pathbufd;
-}
+    /// Opens the file at `self` and lazily yields each of its lines.
+    ///
+    /// The file is read through a [`BufReader`], so it is never loaded into
+    /// memory all at once. Read errors are yielded as [`Err`] items.
+    pub fn lines(&self) -> io::Result<impl Iterator<Item = io::Result<String>>> {
+        Ok(BufReader::new(File::open(&self.0)?).lines())
+    }
 
-#[macro_export]
-macro_rules! pathd {
-    ($($arg:tt)*) => {
-        pathbufd::pathbufd_fmt(std::format_args!($($arg)*)).to_string()
+    /// Creates an empty file at `self` if it doesn't exist, otherwise updates
+    /// its access and modification times to now.
+    ///
+    /// The contents of an existing file are never truncated, and existing files
+    /// are not opened for writing, so read-only files can be touched too.
+    pub fn touch(&self) -> io::Result<()> {
+        let mut existing = OpenOptions::new();
+        existing.read(true);
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+
+            // FILE_WRITE_ATTRIBUTES, needed by `set_times`
+            existing.access_mode(0x100);
+        }
+
+        let file = match existing.open(&self.0) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&self.0)?,
+            Err(e) => return Err(e),
+        };
+
+        let now = SystemTime::now();
+        file.set_times(FileTimes::new().set_accessed(now).set_modified(now))
+    }
+
+    /// Same as [`PathBufD::touch`], but creates any missing parent directories first.
+    pub fn touch_all(&self) -> io::Result<()> {
+        if let Some(parent) = self.0.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        self.touch()
+    }
+
+    /// Gets the size of the file at `self` formatted with binary units and one
+    /// decimal place (`1023 B`, `1.5 KiB`, `3.4 MiB`, ...).
+    pub fn human_size(&self) -> io::Result<String> {
+        Ok(format_size(self.0.metadata()?.len()))
+    }
+
+    /// Creates a new [`PathBufD`] by pushing every part of `parts` in order.
+    ///
+    /// Unlike [`pathd!`], the parts are never converted to a [`String`], so
+    /// components which are not valid UTF-8 are preserved exactly.
+    pub fn from_parts<I, S>(parts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let mut buf = Self::new();
+
+        for part in parts {
+            buf.push(part.as_ref())
+        }
+
+        buf
+    }
+
+    /// Expresses `self` relative to `base`, inserting `..` components where needed.
+    ///
+    /// The computation is purely lexical. Returns [`None`] if `self` and `base`
+    /// are not both absolute or both relative, if they are on different
+    /// prefixes (Windows drives), or if `base` contains a `..` component which
+    /// can't be resolved without touching the filesystem. Equal paths yield `.`.
+    pub fn relative_to<P>(&self, base: P) -> Option<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let base = base.as_ref();
+
+        if self.0.is_absolute() != base.is_absolute() {
+            return None;
+        }
+
+        let mut ita = self.0.components().filter(|c| *c != Component::CurDir);
+        let mut itb = base.components().filter(|c| *c != Component::CurDir);
+        let mut comps: Vec<Component> = Vec::new();
+
+        loop {
+            match (ita.next(), itb.next()) {
+                (None, None) => break,
+                (Some(a), None) => {
+                    comps.push(a);
+                    comps.extend(ita.by_ref());
+                    break;
+                }
+                (None, Some(Component::ParentDir)) => return None,
+                (None, Some(_)) => comps.push(Component::ParentDir),
+                (Some(a), Some(b)) if comps.is_empty() && a == b => {}
+                (Some(Component::Prefix(_)), Some(_)) | (Some(_), Some(Component::Prefix(_))) => {
+                    return None;
+                }
+                (Some(_), Some(Component::ParentDir)) => return None,
+                (Some(a), Some(_)) => {
+                    comps.push(Component::ParentDir);
+
+                    for b in itb.by_ref() {
+                        if b == Component::ParentDir {
+                            return None;
+                        }
+
+                        comps.push(Component::ParentDir);
+                    }
+
+                    comps.push(a);
+                    comps.extend(ita.by_ref());
+                    break;
+                }
+            }
+        }
+
+        if comps.is_empty() {
+            return Some(Self(PathBuf::from(".")));
+        }
+
+        Some(Self(comps.iter().collect()))
+    }
+
+    /// Expresses `self` relative to the current directory if it lies beneath it,
+    /// otherwise returns `self` unchanged.
+    ///
+    /// See [`PathBufD::relative_to`].
+    pub fn relative_from_cwd(&self) -> Self {
+        match self.relative_to(Self::current()) {
+            Some(relative) if !relative.0.starts_with("..") => relative,
+            _ => self.clone(),
+        }
+    }
+
+    /// Collects the non-anchoring components of `self` into [`String`]s.
+    ///
+    /// Unlike [`PathBufD::to_component_vec`], the root and any Windows prefix are
+    /// excluded, so `/a/b/c` and `a/b/c` both yield `["a", "b", "c"]`.
+    /// Components which are not valid UTF-8 are converted lossily.
+    pub fn components_vec(&self) -> Vec<String> {
+        self.0
+            .components()
+            .filter(|c| !matches!(c, Component::Prefix(_) | Component::RootDir))
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Creates an owned [`PathBufD`] with immediately repeated [`Normal`]
+    /// components collapsed into one.
+    ///
+    /// Only adjacent duplicates collapse, so `a/a/b` becomes `a/b` while `a/b/a`
+    /// is left untouched.
+    ///
+    /// [`Normal`]: Component::Normal
+    pub fn dedup_adjacent(&self) -> Self {
+        let mut buf = PathBuf::new();
+        let mut previous: Option<Component> = None;
+
+        for component in self.0.components() {
+            if let (Component::Normal(_), Some(last)) = (component, previous) {
+                if last == component {
+                    continue;
+                }
+            }
+
+            buf.push(component);
+            previous = Some(component);
+        }
+
+        Self(buf)
+    }
+
+    /// Lexically normalizes `self` by removing `.` components and resolving
+    /// `..` components against the preceding component.
+    ///
+    /// This never touches the filesystem, so symlinks are not taken into account
+    /// (unlike [`Path::canonicalize`]). Leading `..` components of a relative path
+    /// are kept, `..` directly after the root is dropped, and a relative path
+    /// which normalizes to nothing becomes `.`.
+    pub fn normalize(&self) -> Self {
+        let mut comps: Vec<Component> = Vec::new();
+
+        for component in self.0.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => match comps.last() {
+                    Some(Component::Normal(_)) => {
+                        comps.pop();
+                    }
+                    Some(Component::Prefix(_) | Component::RootDir) => {}
+                    _ => comps.push(component),
+                },
+                _ => comps.push(component),
+            }
+        }
+
+        if comps.is_empty() {
+            return Self(PathBuf::from("."));
+        }
+
+        Self(comps.iter().collect())
+    }
+
+    /// Checks if `self` lies within `other`, comparing their [normalized] forms.
+    ///
+    /// A path is considered a subpath of itself.
+    ///
+    /// [normalized]: PathBufD::normalize
+    pub fn is_subpath_of<P>(&self, other: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        let this = self.normalize();
+        let other = Self(other.as_ref().to_path_buf()).normalize();
+
+        if other.0 == Path::new(".") {
+            // every relative path which doesn't climb out lies within `.`
+            return this.0.is_relative() && !this.0.starts_with("..");
+        }
+
+        this.0.starts_with(other.0)
+    }
+
+    /// Checks if `other` lies within `self`, comparing their [normalized] forms.
+    ///
+    /// This is the mirror of [`PathBufD::is_subpath_of`], so a path is also
+    /// considered a parent of itself.
+    ///
+    /// [normalized]: PathBufD::normalize
+    pub fn is_parent_of<P>(&self, other: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        Self(other.as_ref().to_path_buf()).is_subpath_of(self)
+    }
+
+    /// Creates a new [`PathBufD`] pointing to the platform's null device
+    /// (`/dev/null` on Unix, `NUL` on Windows).
+    pub fn null_device() -> Self {
+        #[cfg(windows)]
+        {
+            Self(PathBuf::from("NUL"))
+        }
+
+        #[cfg(not(windows))]
+        {
+            Self(PathBuf::from("/dev/null"))
+        }
+    }
+
+    /// Checks if `self` is the platform's null device.
+    ///
+    /// On Windows, `NUL` and `\\.\NUL` are matched case-insensitively.
+    pub fn is_null_device(&self) -> bool {
+        #[cfg(windows)]
+        {
+            self.0.to_str().is_some_and(|s| {
+                s.eq_ignore_ascii_case("NUL") || s.eq_ignore_ascii_case(r"\\.\NUL")
+            })
+        }
+
+        #[cfg(not(windows))]
+        {
+            self.0 == Path::new("/dev/null")
+        }
+    }
+
+    /// Creates an owned [`PathBufD`] with `path` adjoined to self if `path` is
+    /// relative, otherwise returns `path` as-is.
+    ///
+    /// This makes the replacing behavior of [`PathBufD::join`] for absolute
+    /// arguments explicit at the call site.
+    pub fn join_if_relative<P>(&self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        if path.is_relative() {
+            self.join(path)
+        } else {
+            Self(path.to_path_buf())
+        }
+    }
+
+    /// Creates an owned [`PathBufD`] with `path` adjoined to self, failing with
+    /// [`PathError::EscapesBase`] if `path` is anchored (absolute, rooted or
+    /// prefixed) or would [normalize] to somewhere outside of `self`.
+    ///
+    /// [normalize]: PathBufD::normalize
+    pub fn try_join_no_escape<P>(&self, path: P) -> Result<Self, PathError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        if path
+            .components()
+            .any(|c| matches!(c, Component::Prefix(_) | Component::RootDir))
+        {
+            return Err(PathError::EscapesBase);
+        }
+
+        let joined = self.join(path);
+
+        if !joined.is_subpath_of(self) {
+            return Err(PathError::EscapesBase);
+        }
+
+        Ok(joined)
+    }
+
+    /// Gets a [`Display`] wrapper which annotates `self` with its kind and size,
+    /// e.g. `src/main.rs (file, 842 B)` or `missing/path (not found)`.
+    ///
+    /// The filesystem is only queried when the wrapper is formatted.
+    pub fn verbose_display(&self) -> VerboseDisplay<'_> {
+        VerboseDisplay(self)
+    }
+
+    /// Collects every cumulative prefix of `self`, from shortest to longest.
+    ///
+    /// `/a/b/c` yields `[/a, /a/b, /a/b/c]`; the root (and any Windows prefix)
+    /// is attached to the first entry rather than yielded on its own. This is
+    /// the reverse of [`Path::ancestors`], which is useful for breadcrumbs.
+    pub fn prefixes(&self) -> Vec<Self> {
+        let mut out = Vec::new();
+        let mut buf = PathBuf::new();
+
+        for component in self.0.components() {
+            buf.push(component);
+
+            if !matches!(component, Component::Prefix(_) | Component::RootDir) {
+                out.push(Self(buf.clone()));
+            }
+        }
+
+        if out.is_empty() && !buf.as_os_str().is_empty() {
+            out.push(Self(buf));
+        }
+
+        out
+    }
+
+    /// Canonicalizes the longest existing ancestor of `self` and re-appends the
+    /// remaining (not yet existing) components lexically.
+    ///
+    /// This is useful to resolve symlinks in the location of a file which is
+    /// about to be created. A relative path with no existing ancestor is
+    /// resolved against the current directory.
+    pub fn canonicalize_partial(&self) -> io::Result<Self> {
+        let mut error = None;
+
+        for ancestor in self.0.ancestors() {
+            let existing = if ancestor.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                ancestor
+            };
+
+            match existing.canonicalize() {
+                Ok(canonical) => {
+                    let rest = self.0.strip_prefix(ancestor).unwrap_or(Path::new(""));
+
+                    if rest.as_os_str().is_empty() {
+                        return Ok(Self(canonical));
+                    }
+
+                    return Ok(Self(canonical.join(rest)));
+                }
+                Err(e) => error = Some(e),
+            }
+        }
+
+        Err(error.unwrap_or_else(|| io::Error::from(io::ErrorKind::NotFound)))
+    }
+
+    /// Encodes the exact OS representation of `self` for use in wire formats.
+    ///
+    /// The output is a platform tag byte, the byte length of the path as a
+    /// little-endian `u64`, then the raw bytes (UTF-16LE code units on Windows).
+    /// Use [`PathBufD::from_portable_bytes`] to decode it.
+    pub fn to_portable_bytes(&self) -> Vec<u8> {
+        #[cfg(unix)]
+        let raw = self.0.as_os_str().as_bytes().to_vec();
+
+        #[cfg(windows)]
+        let raw: Vec<u8> = self
+            .0
+            .as_os_str()
+            .encode_wide()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+
+        #[cfg(not(any(unix, windows)))]
+        let raw = self.0.to_string_lossy().into_owned().into_bytes();
+
+        let mut out = Vec::with_capacity(9 + raw.len());
+        out.push(PORTABLE_TAG);
+        out.extend_from_slice(&(raw.len() as u64).to_le_bytes());
+        out.extend_from_slice(&raw);
+        out
+    }
+
+    /// Decodes a path encoded by [`PathBufD::to_portable_bytes`].
+    ///
+    /// Fails with [`PathError::PlatformMismatch`] if the path was encoded on a
+    /// different platform family, or [`PathError::Malformed`] if `bytes` is not
+    /// a valid encoding.
+    pub fn from_portable_bytes(bytes: &[u8]) -> Result<Self, PathError> {
+        let (&tag, rest) = bytes.split_first().ok_or(PathError::Malformed)?;
+
+        if tag != PORTABLE_TAG {
+            return Err(PathError::PlatformMismatch);
+        }
+
+        let (len, raw) = rest.split_at_checked(8).ok_or(PathError::Malformed)?;
+        let len = u64::from_le_bytes(len.try_into().map_err(|_| PathError::Malformed)?);
+
+        if raw.len() as u64 != len {
+            return Err(PathError::Malformed);
+        }
+
+        #[cfg(unix)]
+        let os_string = OsStr::from_bytes(raw).to_os_string();
+
+        #[cfg(windows)]
+        let os_string = {
+            if raw.len() % 2 != 0 {
+                return Err(PathError::Malformed);
+            }
+
+            let wide: Vec<u16> = raw
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect();
+
+            OsString::from_wide(&wide)
+        };
+
+        #[cfg(not(any(unix, windows)))]
+        let os_string =
+            OsString::from(String::from_utf8(raw.to_vec()).map_err(|_| PathError::Malformed)?);
+
+        Ok(Self(PathBuf::from(os_string)))
+    }
+
+    /// Re-anchors `self` under `new_root`, so `/etc/hosts` with a root of
+    /// `/sandbox` becomes `/sandbox/etc/hosts`.
+    ///
+    /// Any existing root and Windows prefix (such as a drive letter) are dropped
+    /// before joining. Relative paths are simply joined onto `new_root`.
+    pub fn with_root<P>(&self, new_root: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let mut buf = new_root.as_ref().to_path_buf();
+
+        for component in self.0.components() {
+            if !matches!(component, Component::Prefix(_) | Component::RootDir) {
+                buf.push(component);
+            }
+        }
+
+        Self(buf)
+    }
+
+    /// Computes the structured difference between `self` and `other`.
+    ///
+    /// The result holds the shared leading components, the tail of `self`
+    /// which isn't in `other` (`removed`), and the tail of `other` which isn't
+    /// in `self` (`added`). The comparison is lexical and component-wise.
+    pub fn diff<P>(&self, other: P) -> PathDiff
+    where
+        P: AsRef<Path>,
+    {
+        let mut ita = self.0.components().peekable();
+        let mut itb = other.as_ref().components().peekable();
+        let mut common = PathBuf::new();
+
+        while let (Some(a), Some(b)) = (ita.peek(), itb.peek()) {
+            if a != b {
+                break;
+            }
+
+            common.push(a);
+            ita.next();
+            itb.next();
+        }
+
+        PathDiff {
+            common:  Self(common),
+            removed: Self(ita.collect()),
+            added:   Self(itb.collect()),
+        }
+    }
+
+    /// Recursively iterates over every entry below the directory at `self`.
+    ///
+    /// Directories are yielded before their contents. Symlinks are yielded but
+    /// never followed, so symlinked directories are not descended into.
+    pub fn walk(&self) -> Walk {
+        match fs::read_dir(&self.0) {
+            Ok(read_dir) => Walk {
+                stack: vec![read_dir],
+                error: None,
+            },
+            Err(e) => Walk {
+                stack: Vec::new(),
+                error: Some(e),
+            },
+        }
+    }
+
+    /// Recursively collects every file below the directory at `self` whose
+    /// extension matches `ext` case-insensitively (with or without a leading `.`).
+    ///
+    /// The result is sorted. See [`PathBufD::walk`].
+    pub fn files_with_extension<S>(&self, ext: S) -> io::Result<Vec<Self>>
+    where
+        S: AsRef<str>,
+    {
+        let ext = ext.as_ref().trim_start_matches('.').to_lowercase();
+        let mut out = Vec::new();
+
+        for entry in self.walk() {
+            let entry = entry?;
+
+            let matches = entry
+                .0
+                .extension()
+                .and_then(OsStr::to_str)
+                .is_some_and(|e| e.to_lowercase() == ext);
+
+            if matches && entry.0.symlink_metadata()?.is_file() {
+                out.push(entry);
+            }
+        }
+
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(out)
+    }
+
+    /// Guesses the MIME type of `self` from its extension (`.html` → `text/html`).
+    ///
+    /// This only looks at the extension (case-insensitively) and never reads
+    /// the file's contents. Returns [`None`] for unknown extensions.
+    pub fn guessed_mime(&self) -> Option<&'static str> {
+        let ext = self.0.extension()?.to_str()?;
+
+        MIME_TYPES
+            .iter()
+            .find(|(e, _)| e.eq_ignore_ascii_case(ext))
+            .map(|(_, mime)| *mime)
+    }
+
+    /// Sets the permission bits of the file at `self` to `mode`.
+    ///
+    /// On Windows only the read-only attribute can be changed, so the file is
+    /// marked read-only if `mode` has no write bits set, and writable otherwise.
+    pub fn set_mode(&self, mode: u32) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            fs::set_permissions(&self.0, fs::Permissions::from_mode(mode))
+        }
+
+        #[cfg(not(unix))]
+        {
+            let mut permissions = self.0.metadata()?.permissions();
+            permissions.set_readonly(mode & 0o222 == 0);
+            fs::set_permissions(&self.0, permissions)
+        }
+    }
+
+    /// Adds the execute bits (`+x`) to the permissions of the file at `self`.
+    ///
+    /// On Windows executability is determined by the file extension instead,
+    /// so this only checks that the file exists.
+    pub fn make_executable(&self) -> io::Result<()> {
+        let metadata = self.0.metadata()?;
+
+        #[cfg(unix)]
+        {
+            self.set_mode(metadata.permissions().mode() | 0o111)
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = metadata;
+            Ok(())
+        }
+    }
+
+    /// Removes the extension of [`self.file_name`], if any.
+    ///
+    /// Only the final extension is removed, so `archive.tar.gz` becomes
+    /// `archive.tar`. Returns `true` if an extension was removed.
+    ///
+    /// [`self.file_name`]: Path::file_name
+    pub fn strip_extension(&mut self) -> bool {
+        if self.0.extension().is_none() {
+            return false;
+        }
+
+        self.0.set_extension("")
+    }
+
+    /// Creates an owned [`PathBufD`] with the extension removed.
+    ///
+    /// See [`PathBufD::strip_extension`].
+    pub fn without_extension(&self) -> Self {
+        let mut buf = self.clone();
+        buf.strip_extension();
+        buf
+    }
+
+    /// Lazily iterates over the components of `self` which lie below `ancestor`.
+    ///
+    /// Returns [`None`] if `ancestor` is not a prefix of `self` (see
+    /// [`Path::strip_prefix`]).
+    pub fn components_below<P>(&self, ancestor: P) -> Option<Components<'_>>
+    where
+        P: AsRef<Path>,
+    {
+        self.0
+            .strip_prefix(ancestor)
+            .ok()
+            .map(|rest| rest.components())
+    }
+
+    /// Extends self with `s` encoded into a single component.
+    ///
+    /// See [`encode_component`].
+    pub fn push_encoded(&mut self, s: &str) {
+        self.0.push(encode_component(s))
+    }
+
+    /// Checks if `self` and `other` are equal when `/` and `\` are treated as
+    /// the same separator, regardless of the host platform.
+    ///
+    /// Repeated separators and `.` components are ignored, so `a\b\c`,
+    /// `a/b/c` and `./a//b/c` are all equal.
+    pub fn eq_portable<P>(&self, other: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        portable_segments(&self.0) == portable_segments(other.as_ref())
+    }
+
+    /// Opens the file at `self` in read-only mode (see [`File::open`]).
+    pub fn open(&self) -> io::Result<File> {
+        File::open(&self.0)
+    }
+
+    /// Opens the file at `self` in write-only mode, creating it if it doesn't
+    /// exist and truncating it if it does (see [`File::create`]).
+    pub fn create(&self) -> io::Result<File> {
+        File::create(&self.0)
+    }
+
+    /// Gets an [`OpenOptionsBuilder`] which opens the file at `self`.
+    pub fn options(&self) -> OpenOptionsBuilder<'_> {
+        OpenOptionsBuilder {
+            path:    &self.0,
+            options: OpenOptions::new(),
+        }
+    }
+
+    /// Swaps the leading `old` of `self` for `new`, so `/build/tmp/out/app` with
+    /// `/build/tmp` replaced by `/dist` becomes `/dist/out/app`.
+    ///
+    /// Returns [`None`] if `self` doesn't start with `old` (see [`Path::starts_with`]).
+    pub fn replace_prefix<P, Q>(&self, old: P, new: Q) -> Option<Self>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let rest = self.0.strip_prefix(old).ok()?;
+
+        if rest.as_os_str().is_empty() {
+            return Some(Self(new.as_ref().to_path_buf()));
+        }
+
+        Some(Self(new.as_ref().join(rest)))
+    }
+
+    /// Sums the sizes of every regular file below the directory at `self`, or
+    /// gets the size of the file at `self`.
+    ///
+    /// Symlinks below `self` are never followed, so linked files and
+    /// directories are not counted twice. See [`PathBufD::walk`].
+    pub fn total_size(&self) -> io::Result<u64> {
+        let metadata = self.0.metadata()?;
+
+        if !metadata.is_dir() {
+            return Ok(metadata.len());
+        }
+
+        let mut total = 0;
+
+        for entry in self.walk() {
+            let metadata = entry?.0.symlink_metadata()?;
+
+            if metadata.is_file() {
+                total += metadata.len();
+            }
+        }
+
+        Ok(total)
+    }
+
+    /// Reads the environment variable `name` and splits it into paths using the
+    /// platform's `PATH` separator.
+    ///
+    /// Returns an empty [`Vec`] if the variable is not set. See [`split_env_paths`].
+    pub fn from_env_var(name: &str) -> Vec<Self> {
+        env::var_os(name)
+            .map(|value| split_env_paths(&value))
+            .unwrap_or_default()
+    }
+
+    /// Checks if `self` and `other` refer to the same filesystem location once
+    /// both are [canonicalized], so a symlink and its target are equal.
+    ///
+    /// Fails if either path can't be canonicalized (for example, if it doesn't
+    /// exist).
+    ///
+    /// [canonicalized]: Path::canonicalize
+    pub fn canonical_eq<P>(&self, other: P) -> io::Result<bool>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(self.0.canonicalize()? == other.as_ref().canonicalize()?)
+    }
+
+    /// Creates an owned [`PathBufD`] with ` (n)` inserted before the extensions
+    /// of the file name, so `file.tar.gz` becomes `file (n).tar.gz`.
+    ///
+    /// Names without an extension (including dotfiles like `.env`) get the
+    /// suffix appended. File names which are not valid UTF-8 always get the
+    /// suffix appended.
+    pub fn with_numbered_suffix(&self, n: u32) -> Self {
+        let Some(name) = self.0.file_name() else {
+            return self.clone();
+        };
+
+        let name = match name.to_str() {
+            Some(name) => {
+                let (stem, extensions) = split_at_first_dot(name);
+                OsString::from(format!("{stem} ({n}){extensions}"))
+            }
+            None => {
+                let mut name = name.to_os_string();
+                name.push(format!(" ({n})"));
+                name
+            }
+        };
+
+        Self(self.0.with_file_name(name))
+    }
+
+    /// Gets `self` if nothing exists there yet, otherwise the first path from
+    /// [`PathBufD::with_numbered_suffix`] (starting at 1) which doesn't exist.
+    pub fn next_available(&self) -> io::Result<Self> {
+        if !self.0.try_exists()? {
+            return Ok(self.clone());
+        }
+
+        for n in 1..=u32::MAX {
+            let candidate = self.with_numbered_suffix(n);
+
+            if !candidate.0.try_exists()? {
+                return Ok(candidate);
+            }
+        }
+
+        Err(io::Error::from(io::ErrorKind::AlreadyExists))
+    }
+
+    /// Expands the glob `pattern` relative to the current directory, returning
+    /// the matching paths (relative to it) sorted.
+    ///
+    /// See [`PathBufD::glob_in`] for the supported syntax and how unreadable
+    /// entries are handled. Requires the `glob` feature.
+    #[cfg(feature = "glob")]
+    pub fn glob(pattern: &str) -> io::Result<Vec<Self>> {
+        Ok(Self::glob_in(".", pattern)?
+            .into_iter()
+            .map(|path| match path.0.strip_prefix(".") {
+                Ok(rest) => Self(rest.to_path_buf()),
+                Err(_) => path,
+            })
+            .collect())
+    }
+
+    /// Expands the glob `pattern` relative to `base`, returning the matching
+    /// paths (joined onto `base`) sorted.
+    ///
+    /// Patterns are split on `/`. A `**` segment matches any number of
+    /// directories, and within a segment `*` matches any run of characters,
+    /// `?` matches one character and `[abc]`, `[a-z]` or `[!abc]` match one
+    /// character of a class. Patterns without `**` are only matched against
+    /// entries as deep as the pattern has segments. Symlinked directories are
+    /// not descended into. Requires the `glob` feature.
+    ///
+    /// Entries and subdirectories below `base` which can't be read (such as a
+    /// directory without read permission) are skipped without being reported,
+    /// so the result may be incomplete. Only failing to read `base` itself is
+    /// an error.
+    #[cfg(feature = "glob")]
+    pub fn glob_in<P>(base: P, pattern: &str) -> io::Result<Vec<Self>>
+    where
+        P: AsRef<Path>,
+    {
+        let base = base.as_ref();
+        let pattern: Vec<&str> = pattern
+            .split('/')
+            .filter(|s| !s.is_empty() && *s != ".")
+            .collect();
+
+        let max_depth = if pattern.contains(&"**") {
+            usize::MAX
+        } else {
+            pattern.len()
+        };
+
+        let mut out = Vec::new();
+        let mut stack = vec![(fs::read_dir(base)?, 1)];
+
+        while let Some((read_dir, depth)) = stack.pop() {
+            for entry in read_dir.flatten() {
+                let path = entry.path();
+
+                let Ok(rest) = path.strip_prefix(base) else {
+                    continue;
+                };
+
+                let segments: Vec<String> = rest
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+
+                if depth < max_depth && entry.file_type().is_ok_and(|t| t.is_dir()) {
+                    if let Ok(read_dir) = fs::read_dir(&path) {
+                        stack.push((read_dir, depth + 1));
+                    }
+                }
+
+                if glob_match_segments(&pattern, &segments) {
+                    out.push(Self(path));
+                }
+            }
+        }
+
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(out)
+    }
+
+    /// Gets the number of terminal cells needed to display `self`, as measured by
+    /// [`unicode_width`]. Requires the `unicode-width` feature.
+    ///
+    /// Wide characters (such as CJK ideographs and full-width forms) count as
+    /// two cells, while combining marks count as none. Components which are not
+    /// valid UTF-8 are measured after lossy conversion.
+    #[cfg(feature = "unicode-width")]
+    pub fn display_width(&self) -> usize {
+        unicode_width::UnicodeWidthStr::width(self.0.to_string_lossy().as_ref())
+    }
+
+    /// Extends self with a single component `s` which must not contain separators.
+    ///
+    /// This is a performance escape hatch for hot loops: it appends to the
+    /// underlying buffer directly, skipping the generic [`PathBufD::push`] logic
+    /// for absolute arguments and prefixes. Debug builds assert that `s`
+    /// doesn't contain a separator. Pushing onto a bare Windows prefix (`C:`)
+    /// inserts a separator, unlike [`PathBufD::push`].
+    pub fn push_str(&mut self, s: &str) {
+        debug_assert!(
+            !s.chars().any(path::is_separator),
+            "push_str called with a separator in {s:?}"
+        );
+
+        let needs_separator = self
+            .0
+            .as_os_str()
+            .as_encoded_bytes()
+            .last()
+            .is_some_and(|b| !path::is_separator(*b as char));
+
+        let buf = self.0.as_mut_os_string();
+
+        if needs_separator {
+            buf.push(MAIN_SEPARATOR_STR);
+        }
+
+        buf.push(s);
+    }
+
+    /// Checks if `self` and `other` are anchored the same way: both relative or
+    /// both absolute, and on Windows also on the same drive or UNC share
+    /// (compared case-insensitively).
+    ///
+    /// Use this to guard [`PathBufD::relative_to`] against paths which can't be
+    /// related to each other.
+    pub fn shares_root_with<P>(&self, other: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        fn anchor(path: &Path) -> (Option<String>, bool) {
+            let prefix = match path.components().next() {
+                Some(Component::Prefix(prefix)) => {
+                    Some(prefix.as_os_str().to_string_lossy().to_uppercase())
+                }
+                _ => None,
+            };
+
+            (prefix, path.has_root())
+        }
+
+        anchor(&self.0) == anchor(other.as_ref())
+    }
+
+    /// Converts the absolute path `self` into a `file://` URI, percent-encoding
+    /// its components.
+    ///
+    /// Windows drive paths become `file:///C:/...` and UNC paths become
+    /// `file://server/share/...`. Fails with [`PathError::NotAbsolute`] for
+    /// relative paths, and on Windows with [`PathError::NotUtf8`] for paths
+    /// which are not valid UTF-8.
+    pub fn to_file_uri(&self) -> Result<String, PathError> {
+        if !self.0.is_absolute() {
+            return Err(PathError::NotAbsolute);
+        }
+
+        #[cfg(windows)]
+        {
+            let path = self
+                .0
+                .to_str()
+                .ok_or(PathError::NotUtf8)?
+                .replace('\\', "/");
+            let path = path
+                .strip_prefix("//?/UNC/")
+                .map(|p| format!("//{p}"))
+                .unwrap_or(path);
+            let path = path
+                .strip_prefix("//?/")
+                .map(str::to_string)
+                .unwrap_or(path);
+
+            match path.strip_prefix("//") {
+                Some(unc) => Ok(format!("file://{}", percent_encode_uri(unc.as_bytes()))),
+                None => Ok(format!("file:///{}", percent_encode_uri(path.as_bytes()))),
+            }
+        }
+
+        #[cfg(unix)]
+        {
+            Ok(format!(
+                "file://{}",
+                percent_encode_uri(self.0.as_os_str().as_bytes())
+            ))
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        {
+            let path = self.0.to_str().ok_or(PathError::NotUtf8)?;
+            Ok(format!("file://{}", percent_encode_uri(path.as_bytes())))
+        }
+    }
+
+    /// Parses a `file://` URI into a path, percent-decoding its components.
+    ///
+    /// This is the inverse of [`PathBufD::to_file_uri`]. Any query or fragment is
+    /// ignored. Fails with [`PathError::InvalidUri`] for other schemes, and for
+    /// a host other than `localhost` on platforms without UNC paths.
+    pub fn from_file_uri(uri: &str) -> Result<Self, PathError> {
+        let rest = uri
+            .get(..7)
+            .filter(|scheme| scheme.eq_ignore_ascii_case("file://"))
+            .map(|_| &uri[7..])
+            .ok_or(PathError::InvalidUri)?;
+
+        let rest = rest.split(['?', '#']).next().unwrap_or("");
+        let (host, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => return Err(PathError::InvalidUri),
+        };
+
+        let host = if host.eq_ignore_ascii_case("localhost") {
+            ""
+        } else {
+            host
+        };
+
+        #[cfg(windows)]
+        {
+            let path = String::from_utf8(percent_decode(path)?).map_err(|_| PathError::NotUtf8)?;
+
+            if !host.is_empty() {
+                return Ok(Self(PathBuf::from(format!(
+                    r"\\{host}{}",
+                    path.replace('/', "\\")
+                ))));
+            }
+
+            // `/C:/...` drops its leading slash
+            let bytes = path.as_bytes();
+            let path = if bytes.len() >= 3 && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+                &path[1..]
+            } else {
+                path.as_str()
+            };
+
+            Ok(Self(PathBuf::from(path.replace('/', "\\"))))
+        }
+
+        #[cfg(not(windows))]
+        {
+            if !host.is_empty() {
+                return Err(PathError::InvalidUri);
+            }
+
+            let bytes = percent_decode(path)?;
+
+            #[cfg(unix)]
+            {
+                Ok(Self(PathBuf::from(OsStr::from_bytes(&bytes))))
+            }
+
+            #[cfg(not(unix))]
+            {
+                Ok(Self(PathBuf::from(
+                    String::from_utf8(bytes).map_err(|_| PathError::NotUtf8)?,
+                )))
+            }
+        }
+    }
+
+    /// Watches the directory at `self` (recursively), yielding the path of every
+    /// entry below it which changes. Requires the `watch` feature.
+    ///
+    /// The returned iterator blocks until the next event. Events are forwarded
+    /// from [`notify`] as they arrive, without debouncing: a single save can be
+    /// reported several times (for example, once for the write and once for a
+    /// metadata change), so callers which rebuild on change should debounce
+    /// themselves. Errors reported by the watcher are skipped.
+    #[cfg(feature = "watch")]
+    pub fn watch(&self) -> notify::Result<impl Iterator<Item = Self>> {
+        use notify::Watcher;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(&self.0, notify::RecursiveMode::Recursive)?;
+
+        let mut pending = std::collections::VecDeque::new();
+
+        Ok(std::iter::from_fn(move || {
+            // the watcher stops when dropped, so it lives as long as the iterator
+            let _watcher = &watcher;
+
+            loop {
+                if let Some(path) = pending.pop_front() {
+                    return Some(Self(path));
+                }
+
+                match rx.recv().ok()? {
+                    Ok(event) => pending.extend(event.paths),
+                    Err(_) => continue,
+                }
+            }
+        }))
+    }
+
+    /// Gets a canonical lexical form of `self`, suitable for comparing paths
+    /// without touching the filesystem.
+    ///
+    /// This [normalizes](PathBufD::normalize) the path (which also drops
+    /// trailing and repeated separators), [simplifies](PathBufD::simplified)
+    /// verbatim prefixes, and lowercases it on platforms whose filesystems are
+    /// usually case-insensitive (Windows and macOS). Unlike [`Path::canonicalize`],
+    /// symlinks are never resolved and the path doesn't need to exist, so two
+    /// paths pointing to the same file through a symlink still differ.
+    pub fn lexical_canonical(&self) -> Self {
+        let canonical = self.simplified().normalize();
+
+        #[cfg(any(windows, target_os = "macos"))]
+        {
+            if let Some(s) = canonical.0.to_str() {
+                return Self(PathBuf::from(s.to_lowercase()));
+            }
+        }
+
+        canonical
+    }
+
+    /// Replaces the component at `index` (as yielded by [`Path::components`],
+    /// so the root of an absolute path is index 0) with `value`, rebuilding the
+    /// path.
+    ///
+    /// Returns `false` and does nothing if `index` is out of range, or if
+    /// `value` isn't exactly one plain component (see [`checked_segment`]).
+    pub fn set_component(&mut self, index: usize, value: &OsStr) -> bool {
+        if index >= self.0.components().count() || !is_single_normal_component(value) {
+            return false;
+        }
+
+        let mut buf = PathBuf::new();
+
+        for (i, component) in self.0.components().enumerate() {
+            if i == index {
+                buf.push(value);
+            } else {
+                buf.push(component);
+            }
+        }
+
+        self.0 = buf;
+        true
+    }
+
+    /// Gets [`self.file_name`] as a [`str`].
+    ///
+    /// Returns [`None`] both when there is no file name and when it is not valid
+    /// UTF-8.
+    ///
+    /// [`self.file_name`]: Path::file_name
+    pub fn file_name_str(&self) -> Option<&str> {
+        self.0.file_name()?.to_str()
+    }
+
+    /// Gets [`self.extension`] as a [`str`].
+    ///
+    /// Returns [`None`] both when there is no extension and when it is not valid
+    /// UTF-8.
+    ///
+    /// [`self.extension`]: Path::extension
+    pub fn extension_str(&self) -> Option<&str> {
+        self.0.extension()?.to_str()
+    }
+
+    /// Same as [`PathBufD::relative_to`], but returns a clone of `self` when no
+    /// relative path can be computed.
+    pub fn relative_to_or_self<P>(&self, base: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.relative_to(base).unwrap_or_else(|| self.clone())
+    }
+
+    /// Checks if `self` and `other` are equal once both are [normalized].
+    ///
+    /// [normalized]: PathBufD::normalize
+    pub fn eq_normalized<P>(&self, other: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        self.normalize() == Self(other.as_ref().to_path_buf()).normalize()
+    }
+
+    /// Hashes the [normalized] form of `self`, so `./a/b`, `a/b/` and `a/b`
+    /// all produce the same hash.
+    ///
+    /// This is consistent with [`PathBufD::eq_normalized`], but isn't stable
+    /// across Rust versions. See [`NormalizedPath`] for a map key with the same
+    /// semantics.
+    ///
+    /// [normalized]: PathBufD::normalize
+    pub fn normalized_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.normalize().0.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Checks if the textual form of `self` ends with a separator, as in `dir/`.
+    ///
+    /// This inspects the raw string rather than the components, since
+    /// [`Path::components`] ignores trailing separators.
+    pub fn ends_with_separator(&self) -> bool {
+        self.0
+            .as_os_str()
+            .as_encoded_bytes()
+            .last()
+            .is_some_and(|b| path::is_separator(*b as char))
+    }
+
+    /// Creates an owned [`PathBufD`] whose textual form ends with a separator.
+    ///
+    /// An empty path is returned unchanged, since appending a separator would
+    /// turn it into the root. See [`PathBufD::ends_with_separator`].
+    pub fn ensure_trailing_separator(&self) -> Self {
+        let mut buf = self.clone();
+
+        if !buf.0.as_os_str().is_empty() && !buf.ends_with_separator() {
+            buf.0.as_mut_os_string().push(MAIN_SEPARATOR_STR);
+        }
+
+        buf
+    }
+
+    /// Reads the directory at `self`, pairing each entry with its metadata.
+    ///
+    /// The metadata comes from [`fs::DirEntry::metadata`], which on some
+    /// platforms (like Windows) is returned by the directory read itself and so
+    /// saves a separate `stat` call per entry. Like [`fs::symlink_metadata`],
+    /// symlinks are not followed.
+    pub fn read_dir_with_metadata(&self) -> io::Result<Vec<(Self, fs::Metadata)>> {
+        let mut out = Vec::new();
+
+        for entry in fs::read_dir(&self.0)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            out.push((Self(entry.path()), metadata));
+        }
+
+        Ok(out)
+    }
+
+    /// Converts the absolute path `self` into its verbatim form, which Windows
+    /// APIs accept beyond the legacy `MAX_PATH` limit.
+    ///
+    /// Drive paths get a `\\?\` prefix and UNC shares a `\\?\UNC\` prefix. The
+    /// path is [normalized](PathBufD::normalize) first, since verbatim paths are
+    /// passed to the filesystem as-is. Relative and already verbatim paths (and
+    /// every path on other platforms) are returned unchanged.
+    pub fn to_verbatim(&self) -> Self {
+        #[cfg(windows)]
+        {
+            use std::path::Prefix;
+
+            if let Some(Component::Prefix(prefix)) = self.0.components().next() {
+                if self.0.has_root() {
+                    let normalized = self.normalize();
+
+                    if let Some(s) = normalized.0.to_str() {
+                        match prefix.kind() {
+                            Prefix::Disk(_) => return Self(PathBuf::from(format!(r"\\?\{s}"))),
+                            Prefix::UNC(..) => {
+                                return Self(PathBuf::from(format!(r"\\?\UNC\{}", &s[2..])));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+
+        self.clone()
+    }
+
+    /// Converts a verbatim path back into its regular form, so `\\?\C:\x` becomes
+    /// `C:\x` and `\\?\UNC\server\share` becomes `\\server\share`.
+    ///
+    /// Unlike [`PathBufD::simplified`], the prefix is always removed. Other paths
+    /// (and every path on other platforms) are returned unchanged.
+    pub fn from_verbatim(&self) -> Self {
+        #[cfg(windows)]
+        {
+            if let Some(s) = self.0.to_str() {
+                if let Some(rest) = s.strip_prefix(r"\\?\UNC\") {
+                    return Self(PathBuf::from(format!(r"\\{rest}")));
+                }
+
+                if let Some(rest) = s.strip_prefix(r"\\?\") {
+                    return Self(PathBuf::from(rest));
+                }
+            }
+        }
+
+        self.clone()
+    }
+
+    /// Gets the first component of `self` (see [`Path::components`]).
+    pub fn first_component(&self) -> Option<Component<'_>> {
+        self.0.components().next()
+    }
+
+    /// Classifies how `self` is anchored.
+    pub fn root_kind(&self) -> RootKind {
+        #[cfg(windows)]
+        {
+            use std::path::Prefix;
+
+            if let Some(Component::Prefix(prefix)) = self.0.components().next() {
+                return match prefix.kind() {
+                    Prefix::Disk(d) | Prefix::VerbatimDisk(d) => {
+                        RootKind::WindowsDrive((d as char).to_ascii_uppercase())
+                    }
+                    _ => RootKind::Unc,
+                };
+            }
+        }
+
+        if self.0.as_os_str().is_empty() {
+            RootKind::None
+        } else if self.0.has_root() {
+            RootKind::Unix
+        } else {
+            RootKind::Relative
+        }
+    }
+
+    /// Creates an owned [`PathBufD`] with `path` adjoined to self, reserving
+    /// `expected_total` bytes up front to avoid reallocating while joining.
+    pub fn join_with_capacity<P>(&self, path: P, expected_total: usize) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let mut buf = PathBuf::with_capacity(expected_total.max(self.0.as_os_str().len()));
+        buf.push(&self.0);
+        buf.push(path);
+        Self(buf)
+    }
+
+    /// Creates an owned [`PathBufD`] with every path from `paths` adjoined to self,
+    /// reserving their combined length (plus separators) up front.
+    pub fn join_all_reserving<I, P>(&self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let paths: Vec<P> = paths.into_iter().collect();
+        let total = paths.iter().fold(self.0.as_os_str().len(), |total, path| {
+            total + path.as_ref().as_os_str().len() + 1
+        });
+
+        let mut buf = PathBuf::with_capacity(total);
+        buf.push(&self.0);
+
+        for path in paths {
+            buf.push(path);
+        }
+
+        Self(buf)
+    }
+
+    /// Creates a new [`PathBufD`] from a string literal.
+    ///
+    /// See [`static_path!`] for defining module-level path constants.
+    pub fn from_static(s: &'static str) -> Self {
+        Self(PathBuf::from(s))
+    }
+
+    /// Gets the components of `self` after the first component named `name`, so
+    /// `/home/u/projects/foo/src/lib.rs` after `projects` is `foo/src/lib.rs`.
+    ///
+    /// Returns [`None`] if no component is named `name`. The result may be empty
+    /// if `name` is the last component.
+    pub fn after_component<P>(&self, name: P) -> Option<Self>
+    where
+        P: AsRef<OsStr>,
+    {
+        let name = name.as_ref();
+        let mut components = self.0.components();
+
+        components.find(|c| c.as_os_str() == name)?;
+        Some(Self(components.collect()))
+    }
+
+    /// Gets the components of `self` up to and including the first component
+    /// named `name`, so `/home/u/projects/foo/src/lib.rs` until `projects` is
+    /// `/home/u/projects`.
+    ///
+    /// Returns [`None`] if no component is named `name`.
+    pub fn until_component<P>(&self, name: P) -> Option<Self>
+    where
+        P: AsRef<OsStr>,
+    {
+        let name = name.as_ref();
+        let mut buf = PathBuf::new();
+
+        for component in self.0.components() {
+            buf.push(component);
+
+            if component.as_os_str() == name {
+                return Some(Self(buf));
+            }
+        }
+
+        None
+    }
+
+    /// Converts `self` into a [`String`], reporting where the path stops being
+    /// valid UTF-8 if it isn't.
+    ///
+    /// On Windows, the reported offset is the length in UTF-8 bytes of the valid
+    /// prefix, since paths are stored as UTF-16 there.
+    pub fn to_utf8_string(&self) -> Result<String, Utf8PathError> {
+        #[cfg(unix)]
+        {
+            match std::str::from_utf8(self.0.as_os_str().as_bytes()) {
+                Ok(s) => Ok(s.to_string()),
+                Err(e) => Err(Utf8PathError {
+                    valid_up_to: e.valid_up_to(),
+                }),
+            }
+        }
+
+        #[cfg(windows)]
+        {
+            let mut out = String::new();
+
+            for c in char::decode_utf16(self.0.as_os_str().encode_wide()) {
+                match c {
+                    Ok(c) => out.push(c),
+                    Err(_) => {
+                        return Err(Utf8PathError {
+                            valid_up_to: out.len(),
+                        });
+                    }
+                }
+            }
+
+            Ok(out)
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        {
+            self.0
+                .to_str()
+                .map(str::to_string)
+                .ok_or(Utf8PathError { valid_up_to: 0 })
+        }
+    }
+
+    /// Checks if `self` and `other` are the same file by comparing the device
+    /// and inode numbers on Unix, or the volume serial number and file index on
+    /// Windows.
+    ///
+    /// Unlike [`PathBufD::canonical_eq`], this also recognizes hard links. On
+    /// other platforms this falls back to [`PathBufD::canonical_eq`]. Symlinks
+    /// are followed.
+    pub fn is_same_file<P>(&self, other: P) -> io::Result<bool>
+    where
+        P: AsRef<Path>,
+    {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            let a = self.0.metadata()?;
+            let b = other.as_ref().metadata()?;
+            Ok(a.dev() == b.dev() && a.ino() == b.ino())
+        }
+
+        #[cfg(windows)]
+        {
+            same_file::is_same_file(&self.0, other)
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        {
+            self.canonical_eq(other)
+        }
+    }
+
+    /// Converts the [`PathBufD`] into a [`String`] without copying if it is valid
+    /// UTF-8.
+    ///
+    /// On failure, the original [`PathBufD`] is returned unchanged.
+    pub fn into_string(self) -> Result<String, Self> {
+        self.0
+            .into_os_string()
+            .into_string()
+            .map_err(|s| Self(PathBuf::from(s)))
+    }
+
+    /// Converts the [`PathBufD`] into a [`String`], replacing invalid UTF-8 with
+    /// [`U+FFFD`](char::REPLACEMENT_CHARACTER).
+    ///
+    /// The buffer is reused without copying when it is already valid UTF-8.
+    pub fn into_string_lossy(self) -> String {
+        match self.into_string() {
+            Ok(s) => s,
+            Err(buf) => buf.0.to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Creates an owned [`PathBufD`] with `./` prepended to make a relative path
+    /// explicitly relative, as in `./foo`.
+    ///
+    /// Paths which are absolute, empty or already start with `.` or `..` are
+    /// returned unchanged.
+    pub fn with_dot_prefix(&self) -> Self {
+        match self.0.components().next() {
+            Some(Component::Normal(_)) => Self(Path::new(".").join(&self.0)),
+            _ => self.clone(),
+        }
+    }
+
+    /// Creates an owned [`PathBufD`] with a leading `./` removed, as in `foo`.
+    ///
+    /// Paths which don't start with `./` (including `.` itself) are returned
+    /// unchanged.
+    pub fn strip_dot_prefix(&self) -> Self {
+        match self.0.strip_prefix(".") {
+            Ok(rest) if !rest.as_os_str().is_empty() => Self(rest.to_path_buf()),
+            _ => self.clone(),
+        }
+    }
+
+    /// Gets `self` with every component cased the way it is stored on disk, so
+    /// `/Users/me/DOCUMENTS` becomes `/Users/me/Documents`.
+    ///
+    /// Each parent directory is read to find the entry whose name matches the
+    /// component (exactly, or otherwise case-insensitively). Fails with
+    /// [`io::ErrorKind::NotFound`] if a component has no matching entry.
+    /// Symlinks are not resolved.
+    pub fn canonical_case(&self) -> io::Result<Self> {
+        let mut buf = PathBuf::new();
+
+        for component in self.0.components() {
+            let Component::Normal(name) = component else {
+                buf.push(component);
+                continue;
+            };
+
+            let dir = if buf.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                buf.as_path()
+            };
+
+            let wanted = name.to_string_lossy().to_lowercase();
+            let mut found = None;
+
+            for entry in fs::read_dir(dir)? {
+                let entry_name = entry?.file_name();
+
+                if entry_name == name {
+                    found = Some(entry_name);
+                    break;
+                }
+
+                if found.is_none() && entry_name.to_string_lossy().to_lowercase() == wanted {
+                    found = Some(entry_name);
+                }
+            }
+
+            match found {
+                Some(entry_name) => buf.push(entry_name),
+                None => return Err(io::Error::from(io::ErrorKind::NotFound)),
+            }
+        }
+
+        Ok(Self(buf))
+    }
+
+    /// Renames the children of the directory at `self` to the paths computed by
+    /// `f`, skipping children for which `f` returns [`None`].
+    ///
+    /// Every new name is checked before anything is renamed: if two children
+    /// would get the same name, or a new name is already taken by an entry
+    /// which isn't itself being renamed, this fails with
+    /// [`io::ErrorKind::AlreadyExists`] without touching the filesystem. Swaps
+    /// (`a` to `b` and `b` to `a`) and chains (`a` to `b` and `b` to `c`) are
+    /// supported, since every child is first moved to a temporary name in
+    /// `self` and only then to its new name.
+    ///
+    /// If a rename fails partway through, the renames already done are undone
+    /// (on a best-effort basis) before the error is returned. Returns the
+    /// renamed `(old, new)` pairs, sorted by old path.
+    pub fn rename_children<F>(&self, mut f: F) -> io::Result<Vec<(Self, Self)>>
+    where
+        F: FnMut(&Self) -> Option<Self>,
+    {
+        let mut children = Vec::new();
+
+        for entry in fs::read_dir(&self.0)? {
+            children.push(Self(entry?.path()));
+        }
+
+        children.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let renames: Vec<(Self, Self)> = children
+            .into_iter()
+            .filter_map(|child| f(&child).map(|new| (child, new)))
+            .filter(|(child, new)| child != new)
+            .collect();
+
+        for (i, (_, new)) in renames.iter().enumerate() {
+            let taken =
+                new.0.symlink_metadata().is_ok() && !renames.iter().any(|(old, _)| old == new);
+
+            if taken || renames[..i].iter().any(|(_, other)| other == new) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("rename target {} already exists", new.0.display()),
+                ));
+            }
+        }
+
+        // moves every staged child back to its old name
+        let unstage = |staged: &[PathBuf]| {
+            for ((old, _), stage) in renames.iter().zip(staged).rev() {
+                let _ = fs::rename(stage, &old.0);
+            }
+        };
+
+        let mut staged = Vec::with_capacity(renames.len());
+        let mut n = 0;
+
+        for (old, _) in &renames {
+            let stage = loop {
+                let stage = self
+                    .0
+                    .join(format!(".pathbufd-rename-{}-{n}", std::process::id()));
+                n += 1;
+
+                if stage.symlink_metadata().is_err() {
+                    break stage;
+                }
+            };
+
+            if let Err(e) = fs::rename(&old.0, &stage) {
+                unstage(&staged);
+                return Err(e);
+            }
+
+            staged.push(stage);
+        }
+
+        for (i, ((_, new), stage)) in renames.iter().zip(&staged).enumerate() {
+            if let Err(e) = fs::rename(stage, &new.0) {
+                for ((_, new), stage) in renames[..i].iter().zip(&staged).rev() {
+                    let _ = fs::rename(&new.0, stage);
+                }
+
+                unstage(&staged);
+                return Err(e);
+            }
+        }
+
+        Ok(renames)
+    }
+
+    /// Renders `self` as a printable string which can be parsed back exactly by
+    /// [`PathBufD::from_escaped`].
+    ///
+    /// Backslashes are escaped as `\\`, control characters and bytes which are
+    /// not valid UTF-8 as `\xNN`, and (on Windows) unpaired surrogates as
+    /// `\u{NNNN}`. Everything else is kept as-is. Note that this means Windows
+    /// separators are rendered as `\\` too.
+    pub fn display_escaped(&self) -> String {
+        fn push_escaped(out: &mut String, valid: &str) {
+            for c in valid.chars() {
+                match c {
+                    '\\' => out.push_str(r"\\"),
+                    c if c.is_ascii_control() => out.push_str(&format!(r"\x{:02X}", c as u8)),
+                    c => out.push(c),
+                }
+            }
+        }
+
+        let mut out = String::new();
+
+        #[cfg(unix)]
+        for chunk in self.0.as_os_str().as_bytes().utf8_chunks() {
+            push_escaped(&mut out, chunk.valid());
+
+            for b in chunk.invalid() {
+                out.push_str(&format!(r"\x{b:02X}"));
+            }
+        }
+
+        #[cfg(windows)]
+        for c in char::decode_utf16(self.0.as_os_str().encode_wide()) {
+            match c {
+                Ok(c) => push_escaped(&mut out, c.encode_utf8(&mut [0; 4])),
+                Err(e) => out.push_str(&format!(r"\u{{{:04X}}}", e.unpaired_surrogate())),
+            }
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        push_escaped(&mut out, &self.0.to_string_lossy());
+
+        out
+    }
+
+    /// Parses a string produced by [`PathBufD::display_escaped`].
+    ///
+    /// Fails with [`PathError::Malformed`] if `s` contains an unknown or
+    /// incomplete escape, or an escape which can't be represented on this
+    /// platform.
+    pub fn from_escaped(s: &str) -> Result<Self, PathError> {
+        // paths are stored as UTF-16 code units on Windows, and bytes elsewhere
+        #[cfg(windows)]
+        type Unit = u16;
+        #[cfg(not(windows))]
+        type Unit = u8;
+
+        let mut raw: Vec<Unit> = Vec::with_capacity(s.len());
+
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                #[cfg(windows)]
+                raw.extend(c.encode_utf16(&mut [0; 2]).iter());
+                #[cfg(not(windows))]
+                raw.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                continue;
+            }
+
+            match chars.next() {
+                Some('\\') => raw.push(b'\\' as Unit),
+                Some('x') => {
+                    let hex: String = chars.by_ref().take(2).collect();
+
+                    if hex.len() != 2 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Err(PathError::Malformed);
+                    }
+
+                    let b = u8::from_str_radix(&hex, 16).map_err(|_| PathError::Malformed)?;
+
+                    #[cfg(not(unix))]
+                    if !b.is_ascii() {
+                        return Err(PathError::Malformed);
+                    }
+
+                    raw.push(b as Unit);
+                }
+                #[cfg(windows)]
+                Some('u') => {
+                    if chars.next() != Some('{') {
+                        return Err(PathError::Malformed);
+                    }
+
+                    let mut hex = String::new();
+                    let mut closed = false;
+
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+
+                        hex.push(c);
+                    }
+
+                    if !closed || hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Err(PathError::Malformed);
+                    }
+
+                    raw.push(u16::from_str_radix(&hex, 16).map_err(|_| PathError::Malformed)?);
+                }
+                _ => return Err(PathError::Malformed),
+            }
+        }
+
+        #[cfg(unix)]
+        let os_string = OsStr::from_bytes(&raw).to_os_string();
+
+        #[cfg(windows)]
+        let os_string = OsString::from_wide(&raw);
+
+        #[cfg(not(any(unix, windows)))]
+        let os_string = OsString::from(String::from_utf8(raw).map_err(|_| PathError::Malformed)?);
+
+        Ok(Self(PathBuf::from(os_string)))
+    }
+
+    /// Gets the parent of `self`, falling back to `.` for a bare (or empty)
+    /// relative path and to `self` for a root.
+    pub fn parent_or_current(&self) -> Self {
+        match self.0.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => Self(parent.to_path_buf()),
+            None if self.0.has_root() => self.clone(),
+            _ => Self(PathBuf::from(".")),
+        }
+    }
+
+    /// Inserts `levels` subdirectories of `chars_per_level` leading characters of
+    /// the file name before it, so `abcdef` with 2 levels of 2 characters
+    /// becomes `ab/cd/abcdef`.
+    ///
+    /// Levels are clamped to the length of the file name: only complete levels
+    /// are inserted, so `abc` with 2 levels of 2 characters becomes `ab/abc`.
+    /// Paths without a UTF-8 file name are returned unchanged.
+    pub fn sharded(&self, levels: usize, chars_per_level: usize) -> Self {
+        let Some(name) = self.file_name_str() else {
+            return self.clone();
+        };
+
+        let mut buf = self.0.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        if chars_per_level > 0 {
+            let chars: Vec<char> = name.chars().collect();
+
+            for level in chars.chunks_exact(chars_per_level).take(levels) {
+                buf.push(level.iter().collect::<String>());
+            }
+        }
+
+        buf.push(name);
+        Self(buf)
+    }
+
+    /// Checks if the file at `self` was modified more recently than the file at
+    /// `other`, as in "rebuild if the source is newer than the output".
+    ///
+    /// If `other` doesn't exist, `self` is considered newer.
+    pub fn is_newer_than<P>(&self, other: P) -> io::Result<bool>
+    where
+        P: AsRef<Path>,
+    {
+        let modified = self.0.metadata()?.modified()?;
+
+        match other.as_ref().metadata() {
+            Ok(metadata) => Ok(modified > metadata.modified()?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Checks if the file at `self` was modified after `time`.
+    pub fn modified_since(&self, time: SystemTime) -> io::Result<bool> {
+        Ok(self.0.metadata()?.modified()? > time)
+    }
+
+    /// Converts the relative path `self` into a [`RelativePathBuf`].
+    ///
+    /// Returns [`None`] if `self` is anchored (absolute, rooted or prefixed) or
+    /// not valid UTF-8. Requires the `relative-path` feature.
+    ///
+    /// [`RelativePathBuf`]: relative_path::RelativePathBuf
+    #[cfg(feature = "relative-path")]
+    pub fn to_relative(&self) -> Option<relative_path::RelativePathBuf> {
+        relative_path::RelativePathBuf::from_path(&self.0).ok()
+    }
+
+    /// Creates an owned [`PathBufD`] which is guaranteed to have the extension
+    /// `ext` (with or without a leading `.`).
+    ///
+    /// Unlike [`PathBufD::set_extension`], a different existing extension is
+    /// kept, so `data.csv` becomes `data.csv.gz` while `data.gz` (or `data.GZ`,
+    /// compared case-insensitively) is returned unchanged.
+    pub fn ensure_extension<S>(&self, ext: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        let ext = ext.as_ref().trim_start_matches('.');
+
+        if ext.is_empty()
+            || self
+                .extension_str()
+                .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+        {
+            return self.clone();
+        }
+
+        let Some(name) = self.0.file_name() else {
+            return self.clone();
+        };
+
+        let mut name = name.to_os_string();
+        name.push(".");
+        name.push(ext);
+
+        Self(self.0.with_file_name(name))
+    }
+
+    /// Follows the symlink at `self` a single level, resolving a relative target
+    /// against the link's parent directory.
+    ///
+    /// Unlike [`Path::canonicalize`], the target itself may be another symlink
+    /// (or not exist). Returns `self` unchanged if it isn't a symlink.
+    pub fn resolve_link_once(&self) -> io::Result<Self> {
+        if !self.0.symlink_metadata()?.file_type().is_symlink() {
+            return Ok(self.clone());
+        }
+
+        let target = fs::read_link(&self.0)?;
+
+        match self.0.parent() {
+            Some(parent) if target.is_relative() => Ok(Self(parent.join(target))),
+            _ => Ok(Self(target)),
+        }
+    }
+
+    /// Creates an owned [`PathBufD`] whose file name is at most `max` bytes long,
+    /// as most filesystems limit a single name to 255 bytes.
+    ///
+    /// The name is never cut in the middle of a UTF-8 character, and the final
+    /// extension is kept if it leaves room for at least one character of the
+    /// stem. Paths without a UTF-8 file name, or whose first character doesn't
+    /// fit in `max` bytes (including any `max` of 0), are returned unchanged.
+    pub fn truncate_file_name_bytes(&self, max: usize) -> Self {
+        fn floor_char_boundary(s: &str, max: usize) -> &str {
+            let mut end = max.min(s.len());
+
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+
+            &s[..end]
+        }
+
+        let Some(name) = self.file_name_str() else {
+            return self.clone();
+        };
+
+        if name.len() <= max {
+            return self.clone();
+        }
+
+        let truncated = match (
+            self.0.file_stem().and_then(OsStr::to_str),
+            self.extension_str(),
+        ) {
+            (Some(stem), Some(ext)) if ext.len() + 1 < max => {
+                let stem = floor_char_boundary(stem, max - ext.len() - 1);
+
+                if stem.is_empty() {
+                    floor_char_boundary(name, max).to_string()
+                } else {
+                    format!("{stem}.{ext}")
+                }
+            }
+            _ => floor_char_boundary(name, max).to_string(),
+        };
+
+        if truncated.is_empty() {
+            return self.clone();
+        }
+
+        Self(self.0.with_file_name(truncated))
+    }
+
+    /// Checks if the file at `self` can be executed.
+    ///
+    /// On Unix this checks the execute permission bits, and on Windows the
+    /// extension (`.exe`, `.bat`, `.cmd`, `.com` and anything in `PATHEXT`).
+    /// Directories are never considered executable.
+    pub fn is_executable(&self) -> io::Result<bool> {
+        is_executable_file(&self.0)
+    }
+
+    /// Creates a new [`PathBufD`] from a sequence of borrowed components, such
+    /// as a filtered [`Path::components`].
+    ///
+    /// This is the same as collecting into a [`PathBufD`] (see its
+    /// [`FromIterator`] implementation).
+    pub fn from_components<'a, I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = Component<'a>>,
+    {
+        iter.into_iter().collect()
+    }
+
+    /// Renders `self` with the user's home directory collapsed to `~`, as in
+    /// `~/projects/app`.
+    ///
+    /// The home directory is read from `HOME` (or `USERPROFILE` on Windows).
+    /// Paths outside of it are rendered in full.
+    pub fn display_home_relative(&self) -> String {
+        if let Some(home) = home_dir() {
+            if let Ok(rest) = self.0.strip_prefix(&home) {
+                if rest.as_os_str().is_empty() {
+                    return "~".to_string();
+                }
+
+                return format!("~{MAIN_SEPARATOR_STR}{}", rest.display());
+            }
+        }
+
+        self.0.display().to_string()
+    }
+
+    /// Calls `f` with `self` up to `attempts` times, retrying with a short
+    /// backoff while it fails with a transient error.
+    ///
+    /// [`Interrupted`], [`WouldBlock`] and [`TimedOut`] errors are retried,
+    /// waiting 10 milliseconds longer after each attempt. Any other error is
+    /// returned immediately, and the last error is returned once every attempt
+    /// has failed. At least one attempt is always made.
+    ///
+    /// [`Interrupted`]: io::ErrorKind::Interrupted
+    /// [`WouldBlock`]: io::ErrorKind::WouldBlock
+    /// [`TimedOut`]: io::ErrorKind::TimedOut
+    pub fn with_retries<T, F>(&self, attempts: u32, f: F) -> io::Result<T>
+    where
+        F: Fn(&Path) -> io::Result<T>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            match f(&self.0) {
+                Ok(value) => return Ok(value),
+                Err(e)
+                    if attempt < attempts
+                        && matches!(
+                            e.kind(),
+                            io::ErrorKind::Interrupted
+                                | io::ErrorKind::WouldBlock
+                                | io::ErrorKind::TimedOut
+                        ) =>
+                {
+                    std::thread::sleep(Duration::from_millis(10 * u64::from(attempt)));
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Same as [`PathBufD::normalize`], but borrows `self` instead of allocating
+    /// when it is already normalized.
+    ///
+    /// Paths with a Windows prefix are always normalized to be checked, so
+    /// they allocate even when the result is [`Cow::Borrowed`].
+    pub fn normalize_cow(&self) -> Cow<'_, Self> {
+        if self.is_normalized() {
+            Cow::Borrowed(self)
+        } else {
+            Cow::Owned(self.normalize())
+        }
+    }
+
+    /// Checks if [`PathBufD::normalize`] would return `self` unchanged.
+    fn is_normalized(&self) -> bool {
+        let bytes = self.0.as_os_str().as_encoded_bytes();
+
+        if bytes.is_empty() {
+            return false;
+        }
+
+        if bytes == b"." {
+            return true;
+        }
+
+        let mut previous: Option<Component> = None;
+
+        for component in self.0.components() {
+            match component {
+                Component::Prefix(_) => {
+                    return self.normalize().0.as_os_str() == self.0.as_os_str();
+                }
+                Component::CurDir => return false,
+                Component::ParentDir if !matches!(previous, None | Some(Component::ParentDir)) => {
+                    return false;
+                }
+                _ => {}
+            }
+
+            previous = Some(component);
+        }
+
+        let is_separator = |b: &u8| path::is_separator(*b as char);
+
+        if cfg!(windows) && bytes.contains(&b'/') {
+            return false;
+        }
+
+        if bytes.len() > 1 && bytes.last().is_some_and(is_separator) {
+            return false;
+        }
+
+        !bytes
+            .windows(2)
+            .any(|w| is_separator(&w[0]) && is_separator(&w[1]))
+            // `components` skips interior `.` segments, so look for them directly
+            && !bytes.split(is_separator).any(|segment| segment == b".")
+    }
+
+    /// Splits the Windows prefix (such as `C:` or `\\server\share`) off `self`,
+    /// returning it separately from the remainder (`\a\b`).
+    ///
+    /// The prefix is always [`None`] for paths without one, including every
+    /// path on platforms other than Windows.
+    pub fn split_prefix(&self) -> (Option<Self>, Self) {
+        let mut components = self.0.components();
+
+        match components.next() {
+            Some(Component::Prefix(prefix)) => (
+                Some(Self(PathBuf::from(prefix.as_os_str()))),
+                Self(components.as_path().to_path_buf()),
+            ),
+            _ => (None, self.clone()),
+        }
+    }
+
+    /// Checks if the SHA-256 digest of the file at `self` matches the hex digest
+    /// `expected_hex` (compared case-insensitively).
+    ///
+    /// The file is streamed through the hasher in chunks, so it is never loaded
+    /// into memory all at once. Requires the `sha2` feature.
+    #[cfg(feature = "sha2")]
+    pub fn verify_sha256(&self, expected_hex: &str) -> io::Result<bool> {
+        use sha2::{Digest, Sha256};
+
+        let mut file = File::open(&self.0)?;
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; 64 * 1024];
+
+        loop {
+            match io::Read::read(&mut file, &mut buf) {
+                Ok(0) => break,
+                Ok(n) => hasher.update(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let digest: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        Ok(digest.eq_ignore_ascii_case(expected_hex.trim()))
+    }
+
+    /// Extends self with `segment` after making it safe to use as a single file
+    /// name, skipping it entirely if nothing usable remains.
+    ///
+    /// Separators, characters reserved on Windows and control characters are
+    /// replaced with `_`, trailing dots and spaces are trimmed, and reserved
+    /// Windows device names (`CON`, `NUL`, ...) are prefixed with `_` on every
+    /// platform. Segments which end up empty, `.` or `..` are skipped, so the
+    /// result always stays under `self`.
+    pub fn push_sanitized(&mut self, segment: &str) {
+        let sanitized: String = segment
+            .chars()
+            .map(|c| {
+                if c.is_control()
+                    || matches!(c, '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*')
+                {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect();
+
+        let sanitized = sanitized.trim_end_matches(['.', ' ']);
+
+        if sanitized.is_empty() {
+            return;
+        }
+
+        if is_reserved_windows_name(sanitized) {
+            self.0.push(format!("_{sanitized}"));
+        } else {
+            self.0.push(sanitized);
+        }
+    }
+
+    /// Creates an owned [`PathBufD`] with every segment of `segments` adjoined to
+    /// self through [`PathBufD::push_sanitized`].
+    ///
+    /// Each segment becomes at most one component, so `..` is dropped and `a/b`
+    /// becomes `a_b`, and the result is guaranteed to stay under `self`.
+    pub fn join_sanitized_all<I, S>(&self, segments: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut buf = self.clone();
+
+        for segment in segments {
+            buf.push_sanitized(segment.as_ref());
+        }
+
+        buf
+    }
+
+    /// Renders the shortest sensible human-readable form of `self`.
+    ///
+    /// Verbatim Windows prefixes are [simplified](PathBufD::simplified) first.
+    /// Then, paths below the current directory are rendered relative to it
+    /// (see [`PathBufD::relative_from_cwd`]), and anything else has the home
+    /// directory collapsed to `~` (see [`PathBufD::display_home_relative`]).
+    pub fn short_display(&self) -> String {
+        let simplified = self.simplified();
+        let relative = simplified.relative_from_cwd();
+
+        if relative.0.is_relative() && simplified.0.is_absolute() {
+            return relative.0.display().to_string();
+        }
+
+        simplified.display_home_relative()
+    }
+
+    /// Converts this [`PathBufD`] into an [`Arc<Path>`], so clones only bump a
+    /// reference count.
+    pub fn into_arc(self) -> Arc<Path> {
+        Arc::from(self.0)
+    }
+
+    /// Converts this [`PathBufD`] into an [`Rc<Path>`], so clones only bump a
+    /// reference count.
+    pub fn into_rc(self) -> Rc<Path> {
+        Rc::from(self.0)
+    }
+
+    /// Iterates over the [`OsStr`] of each component of `self`, in order.
+    ///
+    /// Unlike matching on [`Path::components`], every component is yielded the
+    /// same way, so the root of `/a/b` is yielded as `/` before `a` and `b`.
+    pub fn component_strs(&self) -> impl Iterator<Item = &OsStr> {
+        self.0.components().map(|c| c.as_os_str())
+    }
+
+    /// Creates an owned [`PathBufD`] pointing to `{prefix}{random}.{extension}`
+    /// inside the directory at `self`, without creating anything.
+    ///
+    /// The random part is 16 hex characters. The `.` is left out when
+    /// `extension` is empty.
+    pub fn random_child(&self, prefix: &str, extension: &str) -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let mut hasher = RandomState::new().build_hasher();
+        COUNTER
+            .fetch_add(1, AtomicOrdering::Relaxed)
+            .hash(&mut hasher);
+        SystemTime::now().hash(&mut hasher);
+
+        let name = if extension.is_empty() {
+            format!("{prefix}{:016x}", hasher.finish())
+        } else {
+            format!("{prefix}{:016x}.{extension}", hasher.finish())
+        };
+
+        self.join(name)
+    }
+
+    /// Checks if `self` is a directory without any entries.
+    ///
+    /// Only the first entry is read, so this is cheap even for large
+    /// directories. Returns `false` for files, and fails if nothing exists at
+    /// `self`.
+    pub fn is_empty_dir(&self) -> io::Result<bool> {
+        if !self.0.metadata()?.is_dir() {
+            return Ok(false);
+        }
+
+        Ok(fs::read_dir(&self.0)?.next().transpose()?.is_none())
+    }
+
+    /// Removes every empty directory below the directory at `self`, deepest
+    /// first, so directories containing only empty directories are removed too.
+    ///
+    /// `self` itself is never removed. Returns the number of directories
+    /// removed. See [`PathBufD::walk`] and [`PathBufD::is_empty_dir`].
+    pub fn prune_empty_dirs(&self) -> io::Result<usize> {
+        let mut dirs = Vec::new();
+
+        for entry in self.walk() {
+            let entry = entry?;
+
+            if entry.0.symlink_metadata()?.is_dir() {
+                dirs.push(entry);
+            }
+        }
+
+        dirs.sort_by_key(|dir| std::cmp::Reverse(dir.0.components().count()));
+
+        let mut removed = 0;
+
+        for dir in dirs {
+            if dir.is_empty_dir()? {
+                fs::remove_dir(&dir.0)?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Gets the file name of `self` up to its first `.`, so `backup.tar.gz` has a
+    /// full stem of `backup`.
+    ///
+    /// This differs from [`Path::file_stem`], which stops at the last `.`
+    /// (`backup.tar`). A leading dot is part of the stem, so `.env` is its own
+    /// full stem. File names which are not valid UTF-8 fall back to
+    /// [`Path::file_stem`].
+    pub fn file_stem_full(&self) -> Option<&OsStr> {
+        match self.file_name_str() {
+            Some(name) => Some(OsStr::new(split_at_first_dot(name).0)),
+            None => self.0.file_stem(),
+        }
+    }
+
+    /// Creates an owned [`PathBufD`] with every extension of the file name
+    /// removed, so `backup.tar.gz` becomes `backup`.
+    ///
+    /// See [`PathBufD::file_stem_full`].
+    pub fn strip_all_extensions(&self) -> Self {
+        match self.file_stem_full() {
+            Some(stem) => Self(self.0.with_file_name(stem)),
+            None => self.clone(),
+        }
+    }
+
+    /// Gets every extension of the file name after its first `.`, so
+    /// `backup.tar.gz` yields `["tar", "gz"]`.
+    ///
+    /// A leading dot is part of the stem, so `.gitignore` has no extensions.
+    /// Returns an empty [`Vec`] if the file name is missing or not valid UTF-8.
+    pub fn extensions(&self) -> Vec<&str> {
+        let Some(name) = self.file_name_str() else {
+            return Vec::new();
+        };
+
+        match split_at_first_dot(name).1.strip_prefix('.') {
+            Some(extensions) => extensions.split('.').collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Expresses `self` relative to the directory containing `link_location`,
+    /// which is the target to store in a symlink at `link_location` so it keeps
+    /// working when the whole tree is moved.
+    ///
+    /// See [`PathBufD::relative_to`] for when this returns [`None`].
+    pub fn relative_link_target<P>(&self, link_location: P) -> Option<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let link_dir = link_location.as_ref().parent().unwrap_or(Path::new(""));
+        self.relative_to(link_dir)
+    }
+
+    /// Makes `self` absolute by joining it onto the current directory (if it is
+    /// relative) and [normalizing](PathBufD::normalize) the result.
+    ///
+    /// Unlike [`Path::canonicalize`], the path doesn't need to exist and
+    /// symlinks are not resolved. Fails if the current directory can't be read.
+    pub fn absolutize(&self) -> io::Result<Self> {
+        if self.0.is_absolute() {
+            return Ok(self.normalize());
+        }
+
+        Ok(Self(current_dir()?.join(&self.0)).normalize())
+    }
+
+    /// Renders the best absolute form of `self` available, never failing.
+    ///
+    /// This tries [`Path::canonicalize`], then [`PathBufD::absolutize`], and
+    /// finally falls back to `self` as-is. Invalid UTF-8 is rendered lossily, so
+    /// the result is only empty for an empty path which can't be absolutized.
+    pub fn abs_display(&self) -> String {
+        if let Ok(canonical) = self.0.canonicalize() {
+            return canonical.display().to_string();
+        }
+
+        match self.absolutize() {
+            Ok(absolute) => absolute.0.display().to_string(),
+            Err(_) => self.0.display().to_string(),
+        }
+    }
+
+    /// Splits `self` after its first `index` components, so `/a/b/c/d` at 2 is
+    /// split into `/a/b` and `c/d`.
+    ///
+    /// The root and any Windows prefix aren't counted and stay with the first
+    /// half, unless `index` is 0, in which case the first half is empty and the
+    /// second is all of `self`. `index` is clamped to the number of components.
+    pub fn split_at(&self, index: usize) -> (Self, Self) {
+        if index == 0 {
+            return (Self::new(), self.clone());
+        }
+
+        let mut head = PathBuf::new();
+        let mut tail = PathBuf::new();
+        let mut taken = 0;
+
+        for component in self.0.components() {
+            match component {
+                Component::Prefix(_) | Component::RootDir => head.push(component),
+                _ if taken < index => {
+                    head.push(component);
+                    taken += 1;
+                }
+                _ => tail.push(component),
+            }
+        }
+
+        (Self(head), Self(tail))
+    }
+
+    /// Hashes the [normalized] components of `self` with 64-bit FNV-1a, which
+    /// (unlike [`PathBufD::normalized_hash`]) is stable across platforms, builds
+    /// and Rust versions, so it can be used to key on-disk caches.
+    ///
+    /// The raw bytes of each component are hashed with a NUL byte after each, so
+    /// `a/b` and `a\b` on Windows hash the same, and paths which are not valid
+    /// UTF-8 only collide if their bytes are equal. The root is hashed as `/`.
+    /// UTF-8 paths hash the same on every platform; for reference, `a/b` hashes
+    /// to `0xab40d7820d408076`.
+    ///
+    /// [normalized]: PathBufD::normalize
+    pub fn stable_hash(&self) -> u64 {
+        const OFFSET: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+
+        let mut hash = OFFSET;
+
+        for component in self.normalize().0.components() {
+            let bytes = match component {
+                Component::RootDir => b"/",
+                _ => component.as_os_str().as_encoded_bytes(),
+            };
+
+            for &b in bytes.iter().chain(&[0]) {
+                hash ^= u64::from(b);
+                hash = hash.wrapping_mul(PRIME);
+            }
+        }
+
+        hash
+    }
+
+    /// Creates an owned [`PathBufD`] with the current time (in UTC) formatted by
+    /// `fmt` inserted before the extension, so `app.log` with
+    /// `%Y-%m-%dT%H-%M-%S` becomes `app.2024-06-01T12-00-00.log`.
+    ///
+    /// See [`PathBufD::with_timestamp_at`] for the format and when this fails.
+    /// Requires the `chrono` feature.
+    #[cfg(feature = "chrono")]
+    pub fn with_timestamp(&self, fmt: &str) -> Result<Self, PathError> {
+        self.with_timestamp_at(fmt, SystemTime::now())
+    }
+
+    /// Same as [`PathBufD::with_timestamp`], but formats `time` instead of the
+    /// current time. Requires the `chrono` feature.
+    ///
+    /// `fmt` is a [`chrono` format string](chrono::format::strftime), rendered
+    /// in UTC. Names without an extension get the timestamp appended after a
+    /// `.`. Fails with [`PathError::InvalidFormat`] if `fmt` contains a
+    /// specifier `chrono` doesn't support, rather than producing a wrong name.
+    #[cfg(feature = "chrono")]
+    pub fn with_timestamp_at(&self, fmt: &str, time: SystemTime) -> Result<Self, PathError> {
+        use chrono::format::{Item, StrftimeItems};
+
+        let items: Vec<Item> = StrftimeItems::new(fmt).collect();
+
+        if items.contains(&Item::Error) {
+            return Err(PathError::InvalidFormat);
+        }
+
+        let Some(stem) = self.0.file_stem() else {
+            return Ok(self.clone());
+        };
+
+        let timestamp = chrono::DateTime::<chrono::Utc>::from(time)
+            .format_with_items(items.iter())
+            .to_string();
+
+        let mut name = stem.to_os_string();
+        name.push(".");
+        name.push(timestamp);
+
+        if let Some(ext) = self.0.extension() {
+            name.push(".");
+            name.push(ext);
+        }
+
+        Ok(Self(self.0.with_file_name(name)))
+    }
+
+    /// Checks if the path is a symlink whose target does not exist.
+    ///
+    /// Missing paths and symlinks pointing at existing targets both return `false`.
+    pub fn is_broken_symlink(&self) -> io::Result<bool> {
+        match self.0.symlink_metadata() {
+            Ok(meta) if meta.file_type().is_symlink() => {}
+            Ok(_) => return Ok(false),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        }
+
+        match self.0.metadata() {
+            Ok(_) => Ok(false),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates an owned [`PathBufD`] with the non-numeric part of the file stem
+    /// replaced by `new_prefix`, keeping the trailing number and extension
+    /// (`IMG_0042.jpg` with `photo_` becomes `photo_0042.jpg`).
+    ///
+    /// Returns `None` if the stem does not end in a digit (or is not UTF-8). A
+    /// fully numeric stem has an empty prefix, so `new_prefix` is simply put in
+    /// front of it (`0042.jpg` becomes `photo_0042.jpg`).
+    pub fn renumber<S>(&self, new_prefix: S) -> Option<Self>
+    where
+        S: AsRef<str>,
+    {
+        let stem = self.0.file_stem()?.to_str()?;
+        let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+
+        if digits == 0 {
+            return None;
+        }
+
+        let mut name = format!("{}{}", new_prefix.as_ref(), &stem[stem.len() - digits..]);
+
+        if let Some(ext) = self.0.extension() {
+            name.push('.');
+            name.push_str(ext.to_str()?);
+        }
+
+        Some(Self(self.0.with_file_name(name)))
+    }
+
+    /// Counts the components of each kind in the path.
+    ///
+    /// Counts follow [`Path::components`], which collapses repeated separators and
+    /// drops `.` everywhere except at the start, so `./a/../b//c` has one `cur_dir`.
+    pub fn component_summary(&self) -> ComponentSummary {
+        let mut summary = ComponentSummary::default();
+
+        for component in self.0.components() {
+            match component {
+                Component::Normal(_) => summary.normal += 1,
+                Component::CurDir => summary.cur_dir += 1,
+                Component::ParentDir => summary.parent_dir += 1,
+                Component::RootDir => summary.root += 1,
+                Component::Prefix(_) => summary.prefix += 1,
+            }
+        }
+
+        summary
+    }
+
+    /// Creates an owned [`PathBufD`] with `segment` adjoined to `self`, failing
+    /// with [`PathError::NotUtf8`] if `self` is not valid UTF-8.
+    ///
+    /// Since both sides are UTF-8, `to_str` on the result never returns `None`.
+    pub fn join_utf8<S>(&self, segment: S) -> Result<Self, PathError>
+    where
+        S: AsRef<str>,
+    {
+        self.0.to_str().ok_or(PathError::NotUtf8)?;
+        Ok(Self(self.0.join(segment.as_ref())))
+    }
+
+    /// Consumes the [`PathBufD`] and returns an iterator over its components as
+    /// owned strings.
+    ///
+    /// This isn't lazy: every component is copied into a [`Vec`] up front, since
+    /// [`Path::components`] can't outlive the buffer it borrows. Prefer iterating
+    /// over [`Path::components`] when the path is still available.
+    pub fn into_components(self) -> impl Iterator<Item = OsString> {
+        self.0
+            .components()
+            .map(|c| c.as_os_str().to_os_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+impl Display for PathBufD {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.to_str().unwrap_or(""))
+    }
+}
+
+impl Debug for PathBufD {
+    /// Formats as `PathBufD("a/b")`, quoting and escaping the path the same way
+    /// [`Path`]'s [`Debug`] does. The output stays on one line even with `{:#?}`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PathBufD({:?})", self.0.as_path())
+    }
+}
+
+impl AsRef<Path> for PathBufD {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl From<PathBuf> for PathBufD {
+    fn from(value: PathBuf) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(feature = "relative-path")]
+impl From<relative_path::RelativePathBuf> for PathBufD {
+    fn from(value: relative_path::RelativePathBuf) -> Self {
+        Self(value.to_path(""))
+    }
+}
+
+impl From<PathBufD> for Arc<Path> {
+    fn from(value: PathBufD) -> Self {
+        value.into_arc()
+    }
+}
+
+impl From<PathBufD> for Rc<Path> {
+    fn from(value: PathBufD) -> Self {
+        value.into_rc()
+    }
+}
+
+impl PartialEq<OsStr> for PathBufD {
+    fn eq(&self, other: &OsStr) -> bool {
+        self.0.as_os_str() == other
+    }
+}
+
+impl PartialEq<OsString> for PathBufD {
+    fn eq(&self, other: &OsString) -> bool {
+        self.0.as_os_str() == other.as_os_str()
+    }
+}
+
+impl<'a> Extend<Component<'a>> for PathBufD {
+    fn extend<T>(&mut self, iter: T)
+    where
+        T: IntoIterator<Item = Component<'a>>,
+    {
+        for component in iter {
+            self.0.push(component)
+        }
+    }
+}
+
+impl<'a> FromIterator<Component<'a>> for PathBufD {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = Component<'a>>,
+    {
+        let mut buf = Self::new();
+        Extend::extend(&mut buf, iter);
+        buf
+    }
+}
+
+impl From<PathBufD> for PathBuf {
+    fn from(value: PathBufD) -> Self {
+        value.0
+    }
+}
+
+/// [`Display`] wrapper returned by [`PathBufD::verbose_display`]
+pub struct VerboseDisplay<'a>(&'a PathBufD);
+
+impl Display for VerboseDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0.as_path().metadata() {
+            Ok(metadata) if metadata.is_dir() => write!(f, "{} (directory)", self.0),
+            Ok(metadata) if metadata.is_file() => {
+                write!(f, "{} (file, {})", self.0, format_size(metadata.len()))
+            }
+            Ok(_) => write!(f, "{} (other)", self.0),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => write!(f, "{} (not found)", self.0),
+            Err(e) => write!(f, "{} ({e})", self.0),
+        }
+    }
+}
+
+/// Recursive directory iterator returned by [`PathBufD::walk`]
+pub struct Walk {
+    stack: Vec<ReadDir>,
+    error: Option<io::Error>,
+}
+
+impl Iterator for Walk {
+    type Item = io::Result<PathBufD>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.error.take() {
+            return Some(Err(e));
+        }
+
+        loop {
+            let entry = match self.stack.last_mut()?.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            let path = entry.path();
+
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => match fs::read_dir(&path) {
+                    Ok(read_dir) => self.stack.push(read_dir),
+                    Err(e) => self.error = Some(e),
+                },
+                Ok(_) => {}
+                Err(e) => self.error = Some(e),
+            }
+
+            return Some(Ok(PathBufD(path)));
+        }
+    }
+}
+
+/// [`OpenOptions`] bound to a path, returned by [`PathBufD::options`]
+pub struct OpenOptionsBuilder<'a> {
+    path:    &'a Path,
+    options: OpenOptions,
+}
+
+impl OpenOptionsBuilder<'_> {
+    /// Sets the option for read access (see [`OpenOptions::read`]).
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.options.read(read);
+        self
+    }
+
+    /// Sets the option for write access (see [`OpenOptions::write`]).
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.options.write(write);
+        self
+    }
+
+    /// Sets the option for append mode (see [`OpenOptions::append`]).
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.options.append(append);
+        self
+    }
+
+    /// Sets the option for truncating the file (see [`OpenOptions::truncate`]).
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.options.truncate(truncate);
+        self
+    }
+
+    /// Sets the option to create the file if it doesn't exist (see [`OpenOptions::create`]).
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.options.create(create);
+        self
+    }
+
+    /// Sets the option to always create a new file (see [`OpenOptions::create_new`]).
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.options.create_new(create_new);
+        self
+    }
+
+    /// Opens the file with the configured options (see [`OpenOptions::open`]).
+    pub fn open(&self) -> io::Result<File> {
+        self.options.open(self.path)
+    }
+}
+
+/// [`PathBufD`] which compares and hashes by its [normalized] form
+///
+/// [normalized]: PathBufD::normalize
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct NormalizedPath(PathBufD);
+
+impl NormalizedPath {
+    /// Creates a new [`NormalizedPath`] by normalizing `path`.
+    pub fn new<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self(PathBufD(path.as_ref().to_path_buf()).normalize())
+    }
+
+    /// Coerces to a [`Path`] slice.
+    pub fn as_path(&self) -> &Path {
+        self.0.as_path()
+    }
+
+    /// Consumes the [`NormalizedPath`], yielding the normalized [`PathBufD`].
+    pub fn into_inner(self) -> PathBufD {
+        self.0
+    }
+}
+
+impl From<PathBufD> for NormalizedPath {
+    fn from(value: PathBufD) -> Self {
+        Self::new(value)
+    }
+}
+
+impl AsRef<Path> for NormalizedPath {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+/// Classification of how a path is anchored, returned by [`PathBufD::root_kind`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RootKind {
+    /// The path is empty.
+    None,
+    /// The path starts at a root without a prefix (`/a`, or `\a` on Windows).
+    Unix,
+    /// The path starts with a Windows drive letter (`C:\a`), stored uppercase.
+    WindowsDrive(char),
+    /// The path starts with a Windows UNC share (`\\server\share\a`) or another
+    /// device namespace prefix.
+    Unc,
+    /// The path is relative (`a/b`).
+    Relative,
+}
+
+/// Structured difference between two paths, returned by [`PathBufD::diff`]
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct PathDiff {
+    /// The leading components shared by both paths.
+    pub common:  PathBufD,
+    /// The components of the first path after `common`.
+    pub removed: PathBufD,
+    /// The components of the second path after `common`.
+    pub added:   PathBufD,
+}
+
+/// Number of components of each kind in a path, returned by
+/// [`PathBufD::component_summary`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ComponentSummary {
+    /// Plain named components.
+    pub normal:     usize,
+    /// `.` components.
+    pub cur_dir:    usize,
+    /// `..` components.
+    pub parent_dir: usize,
+    /// Root components.
+    pub root:       usize,
+    /// Windows prefix components.
+    pub prefix:     usize,
+}
+
+// errors
+/// Error returned by fallible [`PathBufD`] operations
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PathError {
+    /// The path is not valid UTF-8.
+    NotUtf8,
+    /// The path would resolve outside of its base directory.
+    EscapesBase,
+    /// The encoded path was produced on a different platform family.
+    PlatformMismatch,
+    /// The encoded path is truncated or otherwise malformed.
+    Malformed,
+    /// The path is not absolute.
+    NotAbsolute,
+    /// The URI is not a valid `file://` URI for this platform.
+    InvalidUri,
+    /// The value isn't a single plain path component.
+    InvalidSegment,
+    /// The format string contains an unsupported specifier.
+    InvalidFormat,
+}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotUtf8 => write!(f, "path is not valid UTF-8"),
+            Self::EscapesBase => write!(f, "path escapes its base directory"),
+            Self::PlatformMismatch => write!(f, "path was encoded on a different platform"),
+            Self::Malformed => write!(f, "encoded path is malformed"),
+            Self::NotAbsolute => write!(f, "path is not absolute"),
+            Self::InvalidUri => write!(f, "not a valid file URI"),
+            Self::InvalidSegment => write!(f, "value is not a single path component"),
+            Self::InvalidFormat => write!(f, "format string is not supported"),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// Error returned by [`PathBufD::to_utf8_string`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Utf8PathError {
+    valid_up_to: usize,
+}
+
+impl Utf8PathError {
+    /// Gets the byte offset of the first invalid sequence in the path.
+    pub fn valid_up_to(&self) -> usize {
+        self.valid_up_to
+    }
+}
+
+impl Display for Utf8PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "path is not valid UTF-8 after byte {}", self.valid_up_to)
+    }
+}
+
+impl std::error::Error for Utf8PathError {}
+
+// helpers
+/// Renders `paths` as a `tree`-style hierarchy using box-drawing connectors.
+///
+/// Paths sharing leading components are merged, and entries keep the order in
+/// which they first appear (so `paths` should usually be sorted). Top-level
+/// components are rendered without a connector. Every line ends with `\n`.
+pub fn format_tree(paths: &[PathBufD]) -> String {
+    struct Node {
+        name:     String,
+        children: Vec<Node>,
+    }
+
+    fn render(nodes: &[Node], prefix: &str, out: &mut String) {
+        for (i, node) in nodes.iter().enumerate() {
+            let last = i == nodes.len() - 1;
+
+            out.push_str(prefix);
+            out.push_str(if last { "└── " } else { "├── " });
+            out.push_str(&node.name);
+            out.push('\n');
+
+            render(
+                &node.children,
+                &format!("{prefix}{}", if last { "    " } else { "│   " }),
+                out,
+            );
+        }
+    }
+
+    let mut roots: Vec<Node> = Vec::new();
+
+    for path in paths {
+        let mut level = &mut roots;
+
+        for component in path.0.components() {
+            let name = component.as_os_str().to_string_lossy().into_owned();
+
+            let i = match level.iter().position(|node| node.name == name) {
+                Some(i) => i,
+                None => {
+                    level.push(Node {
+                        name,
+                        children: Vec::new(),
+                    });
+                    level.len() - 1
+                }
+            };
+
+            level = &mut level[i].children;
+        }
+    }
+
+    let mut out = String::new();
+
+    for root in &roots {
+        out.push_str(&root.name);
+        out.push('\n');
+        render(&root.children, "", &mut out);
+    }
+
+    out
+}
+
+/// Platform tag written by [`PathBufD::to_portable_bytes`].
+#[cfg(unix)]
+const PORTABLE_TAG: u8 = 0;
+#[cfg(windows)]
+const PORTABLE_TAG: u8 = 1;
+#[cfg(not(any(unix, windows)))]
+const PORTABLE_TAG: u8 = 2;
+
+/// Extension to MIME type table used by [`PathBufD::guessed_mime`].
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("js", "text/javascript"),
+    ("mjs", "text/javascript"),
+    ("txt", "text/plain"),
+    ("md", "text/markdown"),
+    ("csv", "text/csv"),
+    ("xml", "application/xml"),
+    ("json", "application/json"),
+    ("toml", "application/toml"),
+    ("wasm", "application/wasm"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/vnd.microsoft.icon"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+    ("wav", "audio/wav"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+];
+
+/// Sorts `paths` in place using [`PathBufD::natural_cmp`].
+pub fn sort_natural(paths: &mut [PathBufD]) {
+    paths.sort_by(|a, b| a.natural_cmp(b))
+}
+
+/// Percent-encodes `s` so it can be stored as a single file name.
+///
+/// Separators, characters reserved on Windows, `%`, spaces and control characters
+/// are encoded, as are the dots of `.` and `..`, a trailing dot, and the first
+/// character of reserved Windows device names (`CON`, `nul.txt`, ...). The empty
+/// string is encoded as a lone `%`, which percent-encoding never produces
+/// otherwise. Other characters (including non-ASCII ones) are kept as-is. The
+/// original string is recovered exactly by [`decode_component`].
+pub fn encode_component(s: &str) -> String {
+    if s.is_empty() {
+        return "%".to_string();
+    }
+
+    if s == "." || s == ".." {
+        return s.replace('.', "%2E");
+    }
+
+    let reserved = is_reserved_windows_name(s);
+    let mut out = String::with_capacity(s.len());
+
+    for (i, c) in s.char_indices() {
+        if (i == 0 && reserved)
+            || (c == '.' && i == s.len() - 1)
+            || c.is_ascii_control()
+            || matches!(
+                c,
+                '%' | ' ' | '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'
+            )
+        {
+            out.push_str(&format!("%{:02X}", c as u8));
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+/// Decodes a file name produced by [`encode_component`].
+///
+/// Fails with [`PathError::Malformed`] if `s` contains an invalid escape, or
+/// decodes to something which isn't valid UTF-8.
+pub fn decode_component(s: &str) -> Result<String, PathError> {
+    if s == "%" {
+        return Ok(String::new());
+    }
+
+    String::from_utf8(percent_decode(s)?).map_err(|_| PathError::Malformed)
+}
+
+/// Decodes every `%XX` escape in `s` into its byte.
+fn percent_decode(s: &str) -> Result<Vec<u8>, PathError> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+
+    while let Some(b) = iter.next() {
+        if b != b'%' {
+            bytes.push(b);
+            continue;
+        }
+
+        let hi = iter.next().and_then(|h| (h as char).to_digit(16));
+        let lo = iter.next().and_then(|l| (l as char).to_digit(16));
+
+        match (hi, lo) {
+            (Some(hi), Some(lo)) => bytes.push((hi * 16 + lo) as u8),
+            _ => return Err(PathError::Malformed),
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Percent-encodes every byte of `bytes` which isn't allowed as-is in the path
+/// of a URI (anything other than unreserved characters, `/` and `:`).
+fn percent_encode_uri(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+
+    for &b in bytes {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~' | b'/' | b':') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+
+    out
+}
+
+/// Splits `path` on both `/` and `\`, skipping empty and `.` segments.
+///
+/// A leading separator is kept as an empty first segment.
+fn portable_segments(path: &Path) -> Vec<String> {
+    let string = path.to_string_lossy();
+    let mut segments = Vec::new();
+
+    if string.starts_with(['/', '\\']) {
+        segments.push(String::new());
+    }
+
+    for segment in string.split(['/', '\\']) {
+        if !segment.is_empty() && segment != "." {
+            segments.push(segment.to_string());
+        }
+    }
+
+    segments
+}
+
+/// Splits a `PATH`-style `value` into paths using the platform's separator
+/// (`:` on Unix, `;` on Windows).
+pub fn split_env_paths(value: &OsStr) -> Vec<PathBufD> {
+    env::split_paths(value).map(PathBufD).collect()
+}
+
+/// Joins `paths` into a `PATH`-style value using the platform's separator.
+///
+/// This is the inverse of [`split_env_paths`]. Rather than returning a bare
+/// [`OsString`], this returns a [`Result`] since a path containing the
+/// separator itself can't be joined without changing its meaning.
+pub fn join_env_paths(paths: &[PathBufD]) -> Result<OsString, JoinPathsError> {
+    env::join_paths(paths.iter().map(|path| path.as_path()))
+}
+
+/// Splits a file name into its stem and every extension (including the dot),
+/// so `archive.tar.gz` becomes `("archive", ".tar.gz")`.
+///
+/// A leading dot is part of the stem, so `.env` has no extensions.
+fn split_at_first_dot(name: &str) -> (&str, &str) {
+    let start = usize::from(name.starts_with('.'));
+
+    match name[start..].find('.') {
+        Some(i) => name.split_at(start + i),
+        None => (name, ""),
+    }
+}
+
+/// Matches path `segments` against glob `pattern` segments, where `**` matches
+/// any number of segments.
+#[cfg(feature = "glob")]
+fn glob_match_segments(pattern: &[&str], segments: &[String]) -> bool {
+    match pattern.split_first() {
+        None => segments.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=segments.len()).any(|i| glob_match_segments(rest, &segments[i..]))
+        }
+        Some((p, rest)) => match segments.split_first() {
+            Some((s, segments)) => {
+                let p: Vec<char> = p.chars().collect();
+                let s: Vec<char> = s.chars().collect();
+                glob_match(&p, &s) && glob_match_segments(rest, segments)
+            }
+            None => false,
+        },
+    }
+}
+
+/// Matches a single segment `s` against the wildcard pattern `p`.
+#[cfg(feature = "glob")]
+fn glob_match(p: &[char], s: &[char]) -> bool {
+    match p.first() {
+        None => s.is_empty(),
+        Some('*') => (0..=s.len()).any(|i| glob_match(&p[1..], &s[i..])),
+        Some('?') => !s.is_empty() && glob_match(&p[1..], &s[1..]),
+        Some('[') => {
+            let negated = p.get(1) == Some(&'!');
+            let start = if negated { 2 } else { 1 };
+
+            // a `]` right after the opening bracket is part of the class
+            let Some(end) = p
+                .iter()
+                .skip(start + 1)
+                .position(|c| *c == ']')
+                .map(|i| i + start + 1)
+            else {
+                return s.first() == Some(&'[') && glob_match(&p[1..], &s[1..]);
+            };
+
+            let Some(c) = s.first() else {
+                return false;
+            };
+
+            let class = &p[start..end];
+            let mut matched = false;
+            let mut i = 0;
+
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    matched |= (class[i]..=class[i + 2]).contains(c);
+                    i += 3;
+                } else {
+                    matched |= class[i] == *c;
+                    i += 1;
+                }
+            }
+
+            matched != negated && glob_match(&p[end + 1..], &s[1..])
+        }
+        Some(c) => s.first() == Some(c) && glob_match(&p[1..], &s[1..]),
+    }
+}
+
+/// Resolves the executable `program` against the directories in `PATH`,
+/// returning the first match.
+///
+/// On Windows, each extension in `PATHEXT` is tried as well. A `program`
+/// containing a separator is checked as-is instead of being searched for.
+pub fn which<S>(program: S) -> Option<PathBufD>
+where
+    S: AsRef<str>,
+{
+    which_in(program.as_ref(), env::var_os("PATH").as_deref())
+}
+
+/// Same as [`which`], but searches the `PATH`-style `search_path` instead of
+/// reading `PATH`.
+fn which_in(program: &str, search_path: Option<&OsStr>) -> Option<PathBufD> {
+    if program.is_empty() {
+        return None;
+    }
+
+    let candidates = |path: PathBuf| -> Vec<PathBuf> {
+        #[cfg(windows)]
+        {
+            let mut out = vec![path.clone()];
+            let pathext = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+
+            for ext in pathext.split(';').filter(|ext| !ext.is_empty()) {
+                let mut candidate = path.clone().into_os_string();
+                candidate.push(ext);
+                out.push(PathBuf::from(candidate));
+            }
+
+            out
+        }
+
+        #[cfg(not(windows))]
+        {
+            vec![path]
+        }
+    };
+
+    if program.contains(path::is_separator) {
+        return candidates(PathBuf::from(program))
+            .into_iter()
+            .find(|path| is_executable_file(path).unwrap_or(false))
+            .map(PathBufD);
+    }
+
+    env::split_paths(search_path?)
+        .flat_map(|dir| candidates(dir.join(program)))
+        .find(|path| is_executable_file(path).unwrap_or(false))
+        .map(PathBufD)
+}
+
+/// Gets the current user's home directory from the environment.
+fn home_dir() -> Option<PathBuf> {
+    #[cfg(windows)]
+    let home = env::var_os("USERPROFILE");
+
+    #[cfg(not(windows))]
+    let home = env::var_os("HOME");
+
+    home.filter(|home| !home.is_empty()).map(PathBuf::from)
+}
+
+/// Checks if `path` is a file which can be executed (see [`PathBufD::is_executable`]).
+fn is_executable_file(path: &Path) -> io::Result<bool> {
+    let metadata = path.metadata()?;
+
+    if !metadata.is_file() {
+        return Ok(false);
+    }
+
+    #[cfg(unix)]
+    {
+        Ok(metadata.permissions().mode() & 0o111 != 0)
+    }
+
+    #[cfg(windows)]
+    {
+        let Some(ext) = path.extension().and_then(OsStr::to_str) else {
+            return Ok(false);
+        };
+
+        let pathext = env::var("PATHEXT").unwrap_or_default();
+
+        let executable = ["exe", "bat", "cmd", "com"]
+            .into_iter()
+            .chain(pathext.split(';').map(|e| e.trim_start_matches('.')))
+            .any(|e| !e.is_empty() && e.eq_ignore_ascii_case(ext));
+
+        Ok(executable)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        Ok(true)
+    }
+}
+
+/// Compares two strings, treating runs of ASCII digits as numbers.
+fn natural_cmp_str(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let mut x_run = String::new();
+                while let Some(c) = a.next_if(char::is_ascii_digit) {
+                    x_run.push(c);
+                }
+
+                let mut y_run = String::new();
+                while let Some(c) = b.next_if(char::is_ascii_digit) {
+                    y_run.push(c);
+                }
+
+                let x_trimmed = x_run.trim_start_matches('0');
+                let y_trimmed = y_run.trim_start_matches('0');
+
+                let ordering = x_trimmed
+                    .len()
+                    .cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed))
+                    .then_with(|| x_run.len().cmp(&y_run.len()));
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+
+                a.next();
+                b.next();
+            }
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
+
+/// Checks if the verbatim prefix of `path` can be removed without changing its meaning.
+#[cfg(windows)]
+fn is_safe_to_strip_verbatim(path: &Path) -> bool {
+    use std::path::{Component, Prefix};
+
+    let mut components = path.components();
+
+    match components.next() {
+        Some(Component::Prefix(prefix)) => {
+            if !matches!(prefix.kind(), Prefix::VerbatimDisk(_)) {
+                return false;
+            }
+        }
+        _ => return false,
+    }
+
+    for component in components {
+        match component {
+            Component::RootDir => {}
+            Component::Normal(name) => {
+                let Some(name) = name.to_str() else {
+                    return false;
+                };
+
+                if !is_valid_windows_file_name(name) || is_reserved_windows_name(name) {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+
+    // MAX_PATH counts UTF-16 units, and `\\?\` doesn't count once stripped
+    path.as_os_str().encode_wide().count() - 4 < 260
+}
+
+/// Checks if `name` is usable as a file name by legacy Windows APIs.
+#[cfg(windows)]
+fn is_valid_windows_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 255
+        && !name.ends_with(['.', ' '])
+        && !name
+            .chars()
+            .any(|c| c < ' ' || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*'))
+}
+
+/// Checks if `name` is a reserved Windows device name (`CON`, `NUL`, `COM1`, ...).
+fn is_reserved_windows_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or("").trim_end_matches(' ');
+    let upper = stem.to_ascii_uppercase();
+
+    match upper.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => {
+            (upper.starts_with("COM") || upper.starts_with("LPT"))
+                && upper.len() == 4
+                && matches!(upper.as_bytes()[3], b'1'..=b'9')
+        }
+    }
+}
+
+/// Checks if `value` is parsed as exactly one [`Component::Normal`] equal to
+/// itself, and contains no separator (`/` or `\` on every platform) or NUL byte.
+fn is_single_normal_component(value: &OsStr) -> bool {
+    if value
+        .as_encoded_bytes()
+        .iter()
+        .any(|b| matches!(b, b'/' | b'\\' | b'\0'))
+    {
+        return false;
+    }
+
+    let mut components = Path::new(value).components();
+
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(name)), None) if name == value
+    )
+}
+
+/// Formats `bytes` using binary units.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+// macro
+/// Format [`Arguments`] into a [`PathBufD`]
+///
+/// The arguments are always formatted into a UTF-8 [`String`] before being split
+/// on `/`, so every component of the result is valid UTF-8. Use
+/// [`PathBufD::from_parts`] to build a path from non-UTF-8 components.
+pub fn pathbufd_fmt(args: Arguments) -> PathBufD {
+    let string = if let Some(s) = args.as_str() {
+        s
+    } else {
+        &args.to_string()
+    };
+
+    let mut pathbufd = PathBufD::new();
+    for split in string.split("/") {
+        if split.is_empty() {
+            pathbufd.push("/");
+            continue;
+        }
+
+        pathbufd.push(split);
+    }
+
+    pathbufd
+}
+
+/// Formats `value` and checks that it is a single plain path component.
+///
+/// Fails with [`PathError::InvalidSegment`] if the result is empty, `.` or `..`,
+/// contains a separator (`/` or `\` on every platform) or a NUL byte, or would
+/// otherwise not be parsed as exactly one normal component (such as a Windows
+/// drive prefix). Used by [`pathd_safe!`].
+pub fn checked_segment<T>(value: &T) -> Result<String, PathError>
+where
+    T: Display + ?Sized,
+{
+    let segment = value.to_string();
+
+    if !is_single_normal_component(OsStr::new(&segment)) {
+        return Err(PathError::InvalidSegment);
+    }
+
+    Ok(segment)
+}
+
+/// Checks that every placeholder of the format string `fmt` refers to a
+/// positional argument (`{}`, `{0}`, `{:>4}`), not an inline capture (`{name}`).
+/// Used by [`pathd_safe!`].
+#[doc(hidden)]
+pub const fn is_positional_format(fmt: &str) -> bool {
+    let bytes = fmt.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'{' {
+            if i + 1 < bytes.len() && bytes[i + 1] == b'{' {
+                i += 2;
+                continue;
+            }
+
+            if i + 1 < bytes.len() && !matches!(bytes[i + 1], b'}' | b':' | b'0'..=b'9') {
+                return false;
+            }
+        }
+
+        i += 1;
+    }
+
+    true
+}
+
+#[macro_export]
+macro_rules! pathd {
+    ($($arg:tt)*) => {
+        pathbufd::pathbufd_fmt(std::format_args!($($arg)*)).to_string()
+    }
+}
+
+/// Same as [`pathd!`], but checks every positional argument with [`checked_segment`],
+/// returning a [`Result`] of the formatted [`PathBufD`].
+///
+/// Only the interpolated arguments are checked, so separators in the format
+/// string itself are kept. Arguments captured inline (`{name}`) would bypass
+/// the check, so they are rejected at compile time.
+#[macro_export]
+macro_rules! pathd_safe {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {{
+        const _: () = ::std::assert!(
+            $crate::is_positional_format($fmt),
+            "pathd_safe! only supports positional arguments (`{{}}` or `{{0}}`)"
+        );
+
+        (|| -> ::std::result::Result<$crate::PathBufD, $crate::PathError> {
+            ::std::result::Result::Ok($crate::pathbufd_fmt(::std::format_args!(
+                $fmt
+                $(, $crate::checked_segment(&$arg)?)*
+            )))
+        })()
+    }};
+}
+
+/// Defines lazily initialized `static` [`PathBufD`]s from string literals.
+///
+/// Each path is created with [`PathBufD::from_static`] the first time it is
+/// dereferenced, and only once (see [`LazyLock`](std::sync::LazyLock)).
+///
+/// ```rust
+/// static_path! {
+///     pub CONFIG = "config/app.toml";
+///     CACHE = "cache";
+/// }
+/// ```
+#[macro_export]
+macro_rules! static_path {
+    ($($(#[$meta:meta])* $vis:vis $name:ident = $path:expr;)*) => {
+        $(
+            $(#[$meta])*
+            $vis static $name: ::std::sync::LazyLock<$crate::PathBufD> =
+                ::std::sync::LazyLock::new(|| $crate::PathBufD::from_static($path));
+        )*
+    };
+}
+
+/// Asserts that `path` lies within `base` once both are normalized (see
+/// [`PathBufD::is_subpath_of`]), panicking with both paths otherwise.
+///
+/// Both arguments can be anything implementing `AsRef<Path>`. An optional
+/// format string and arguments can be given to add to the message, like
+/// [`assert!`].
+#[macro_export]
+macro_rules! assert_within {
+    ($path:expr, $base:expr $(,)?) => {{
+        let (path, base) = (&$path, &$base);
+        let path: &::std::path::Path = ::std::convert::AsRef::as_ref(path);
+        let base: &::std::path::Path = ::std::convert::AsRef::as_ref(base);
+
+        if !$crate::PathBufD::from(path.to_path_buf()).is_subpath_of(base) {
+            ::std::panic!(
+                "assertion failed: `{}` is not within `{}`",
+                path.display(),
+                base.display()
+            );
+        }
+    }};
+    ($path:expr, $base:expr, $($arg:tt)+) => {{
+        let (path, base) = (&$path, &$base);
+        let path: &::std::path::Path = ::std::convert::AsRef::as_ref(path);
+        let base: &::std::path::Path = ::std::convert::AsRef::as_ref(base);
+
+        if !$crate::PathBufD::from(path.to_path_buf()).is_subpath_of(base) {
+            ::std::panic!(
+                "assertion failed: `{}` is not within `{}`: {}",
+                path.display(),
+                base.display(),
+                ::std::format_args!($($arg)+)
+            );
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serializes tests which read or change the current directory or
+    /// environment variables.
+    static CWD_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    fn lock_cwd() -> std::sync::MutexGuard<'static, ()> {
+        CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Empty directory unique to the calling test, removed when dropped
+    struct TempDir(PathBufD);
+
+    impl std::ops::Deref for TempDir {
+        type Target = PathBufD;
+
+        fn deref(&self) -> &PathBufD {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempDir {
+        fn as_ref(&self) -> &Path {
+            self.0.as_path()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(self.0.as_path());
+        }
+    }
+
+    /// Creates an empty directory unique to the calling test.
+    fn temp_dir(name: &str) -> TempDir {
+        let dir = env::temp_dir().join(format!("pathbufd-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(PathBufD::from(dir))
+    }
+
+    #[test]
+    fn component_vec_round_trip() {
+        for path in ["a/b/c", "/usr/local/bin", "../x/./y", ""] {
+            let path = PathBufD::new().join(path);
+            assert_eq!(PathBufD::from_component_vec(&path.to_component_vec()), path);
+        }
+
+        assert_eq!(
+            PathBufD::new().join("/a/b").to_component_vec(),
+            ["/", "a", "b"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn component_vec_is_lossy_for_non_utf8() {
+        let path = PathBufD::from(PathBuf::from(OsStr::from_bytes(b"a/\xffb")));
+        let parts = path.to_component_vec();
+
+        assert_eq!(parts, ["a", "\u{fffd}b"]);
+        assert_ne!(PathBufD::from_component_vec(&parts), path);
+    }
+
+    #[test]
+    fn natural_cmp_orders_numbers() {
+        let mut paths: Vec<PathBufD> = ["file10", "file2", "file1"]
+            .into_iter()
+            .map(|p| PathBufD::new().join(p))
+            .collect();
+        sort_natural(&mut paths);
+
+        assert_eq!(
+            paths,
+            [
+                PathBufD::new().join("file1"),
+                PathBufD::new().join("file2"),
+                PathBufD::new().join("file10"),
+            ]
+        );
+    }
+
+    #[test]
+    fn natural_cmp_orders_alphabetic_names() {
+        let mut paths: Vec<PathBufD> = ["cherry", "apple", "banana", "dir/b", "dir/a"]
+            .into_iter()
+            .map(|p| PathBufD::new().join(p))
+            .collect();
+        sort_natural(&mut paths);
+
+        assert_eq!(
+            paths.iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+            ["apple", "banana", "cherry", "dir/a", "dir/b"]
+        );
+        assert_eq!(
+            PathBufD::new()
+                .join("a/b")
+                .natural_cmp(&PathBufD::new().join("a/b/c")),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn from_utf8_checked_accepts_utf8() {
+        assert_eq!(
+            PathBufD::from_utf8_checked("dir/файл.txt"),
+            Ok(PathBufD::new().join("dir/файл.txt"))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn from_utf8_checked_rejects_invalid_bytes() {
+        let s = OsStr::from_bytes(b"bad\xff").to_os_string();
+        assert_eq!(PathBufD::from_utf8_checked(s), Err(PathError::NotUtf8));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn from_utf8_checked_rejects_unpaired_surrogate() {
+        let s = OsString::from_wide(&[0x61, 0xd800]);
+        assert_eq!(PathBufD::from_utf8_checked(s), Err(PathError::NotUtf8));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn simplified_is_identity() {
+        let path = PathBufD::new().join(r"\\?\C:\dir");
+        assert_eq!(path.simplified(), path);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn simplified_strips_short_verbatim_paths() {
+        assert_eq!(
+            PathBufD::new()
+                .join(r"\\?\C:\Users\me\file.txt")
+                .simplified(),
+            PathBufD::new().join(r"C:\Users\me\file.txt")
+        );
+
+        // 100 non-ASCII characters are 200 bytes but only 100 UTF-16 units
+        let name = "é".repeat(100);
+        assert_eq!(
+            PathBufD::new().join(format!(r"\\?\C:\{name}")).simplified(),
+            PathBufD::new().join(format!(r"C:\{name}"))
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn simplified_keeps_verbatim_when_needed() {
+        let long = format!(r"\\?\C:\{}\{}", "a".repeat(200), "b".repeat(100));
+        let reserved = r"\\?\C:\dir\CON";
+        let trailing_dot = r"\\?\C:\dir\name.";
+
+        for path in [long.as_str(), reserved, trailing_dot] {
+            let path = PathBufD::new().join(path);
+            assert_eq!(path.simplified(), path);
+        }
+    }
+
+    #[test]
+    fn render_with_separator_uses_sep() {
+        let absolute = PathBufD::new().join("/a/b/c");
+        assert_eq!(absolute.render_with_separator('/'), "/a/b/c");
+        assert_eq!(absolute.render_with_separator('\\'), "\\a\\b\\c");
+        assert_eq!(absolute.render_with_separator(':'), ":a:b:c");
+
+        let relative = PathBufD::new().join("a/b/c");
+        assert_eq!(relative.render_with_separator(':'), "a:b:c");
+    }
+
+    #[test]
+    fn lines_yields_each_line() {
+        let dir = temp_dir("lines");
+        let file = dir.join("log.txt");
+        fs::write(&file, "one\ntwo\r\nthree").unwrap();
+
+        let lines: Vec<String> = file.lines().unwrap().map(Result::unwrap).collect();
+        assert_eq!(lines, ["one", "two", "three"]);
+    }
+
+    #[test]
+    fn lines_surfaces_read_errors() {
+        let dir = temp_dir("lines-error");
+        let file = dir.join("log.txt");
+        fs::write(&file, b"ok\n\xff\xfe\nlater\n").unwrap();
+
+        let mut lines = file.lines().unwrap();
+        assert_eq!(lines.next().unwrap().unwrap(), "ok");
+        assert_eq!(
+            lines.next().unwrap().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn touch_creates_missing_file() {
+        let dir = temp_dir("touch-new");
+        let file = dir.join("new.txt");
+        file.touch().unwrap();
+        assert_eq!(fs::read(&file).unwrap(), b"");
+
+        let nested = dir.join("a/b/c.txt");
+        assert!(nested.touch().is_err());
+        nested.touch_all().unwrap();
+        assert!(nested.as_path().is_file());
+    }
+
+    #[test]
+    fn touch_preserves_contents_and_advances_mtime() {
+        let dir = temp_dir("touch-existing");
+        let file = dir.join("data.txt");
+        fs::write(&file, "keep me").unwrap();
+
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+
+        let mut permissions = fs::metadata(&file).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&file, permissions.clone()).unwrap();
+
+        file.touch().unwrap();
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "keep me");
+        assert!(fs::metadata(&file).unwrap().modified().unwrap() > old);
+
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(&file, permissions).unwrap();
+    }
+
+    #[test]
+    fn human_size_thresholds() {
+        let dir = temp_dir("human-size");
+
+        for (len, expected) in [
+            (0, "0 B"),
+            (1023, "1023 B"),
+            (1024, "1.0 KiB"),
+            (1536, "1.5 KiB"),
+            (3 * 1024 * 1024 + 400 * 1024, "3.4 MiB"),
+        ] {
+            let file = dir.join(format!("{len}.bin"));
+            File::create(&file).unwrap().set_len(len).unwrap();
+            assert_eq!(file.human_size().unwrap(), expected);
+        }
+
+        assert!(dir.join("missing").human_size().is_err());
+    }
+
+    #[test]
+    fn inner_accessors() {
+        let mut path = PathBufD::new().join("a/b.txt");
+        assert_eq!(path.inner(), &PathBuf::from("a/b.txt"));
+
+        path.inner_mut().set_extension("md");
+        path.inner_mut().push("c");
+        assert_eq!(path, PathBufD::new().join("a/b.md/c"));
+
+        assert_eq!(path.into_inner(), PathBuf::from("a/b.md/c"));
+    }
+
+    #[test]
+    fn from_parts_joins_os_strings() {
+        let parts = [
+            OsString::from("a"),
+            OsString::from("b"),
+            OsString::from("c.txt"),
+        ];
+        assert_eq!(
+            PathBufD::from_parts(&parts),
+            PathBufD::new().join("a/b/c.txt")
+        );
+        assert_eq!(PathBufD::from_parts(Vec::<&str>::new()), PathBufD::new());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn from_parts_preserves_non_utf8() {
+        let bad = OsStr::from_bytes(b"caf\xe9");
+        let path = PathBufD::from_parts([OsStr::new("dir"), bad]);
+
+        assert_eq!(path.as_path().as_os_str().as_bytes(), b"dir/caf\xe9");
+        assert_eq!(path.as_path().file_name(), Some(bad));
+    }
+
+    #[test]
+    fn relative_to_lexical() {
+        let path = PathBufD::new().join("/a/b/c");
+        assert_eq!(path.relative_to("/a"), Some(PathBufD::new().join("b/c")));
+        assert_eq!(
+            path.relative_to("/a/x"),
+            Some(PathBufD::new().join("../b/c"))
+        );
+        assert_eq!(path.relative_to("/a/b/c"), Some(PathBufD::new().join(".")));
+        assert_eq!(path.relative_to("a"), None);
+    }
+
+    #[test]
+    fn relative_from_cwd_strips_cwd() {
+        let _guard = lock_cwd();
+        let previous = env::current_dir().unwrap();
+        let dir = temp_dir("relative-cwd");
+
+        env::set_current_dir(&dir).unwrap();
+        let cwd = PathBufD::current();
+        let child = cwd.join("src/main.rs");
+        let relative = child.relative_from_cwd();
+        let unrelated = PathBufD::new()
+            .join(cwd.as_path().parent().unwrap())
+            .join("elsewhere");
+        let outside = unrelated.relative_from_cwd();
+        env::set_current_dir(previous).unwrap();
+
+        assert_eq!(relative, PathBufD::new().join("src/main.rs"));
+        assert_eq!(outside, unrelated);
+    }
+
+    #[test]
+    fn components_vec_excludes_root() {
+        assert_eq!(
+            PathBufD::new().join("a/b/c").components_vec(),
+            ["a", "b", "c"]
+        );
+        assert_eq!(
+            PathBufD::new().join("/a/b/c").components_vec(),
+            ["a", "b", "c"]
+        );
+        assert!(PathBufD::new().join("/").components_vec().is_empty());
+    }
+
+    #[test]
+    fn dedup_adjacent_collapses_repeats() {
+        assert_eq!(
+            PathBufD::new().join("cache/cache/images").dedup_adjacent(),
+            PathBufD::new().join("cache/images")
+        );
+        assert_eq!(
+            PathBufD::new().join("a/a/b").dedup_adjacent(),
+            PathBufD::new().join("a/b")
+        );
+        assert_eq!(
+            PathBufD::new().join("x/x/x/x").dedup_adjacent(),
+            PathBufD::new().join("x")
+        );
+    }
+
+    #[test]
+    fn dedup_adjacent_keeps_non_adjacent() {
+        let path = PathBufD::new().join("a/b/a");
+        assert_eq!(path.dedup_adjacent(), path);
+        assert_eq!(
+            PathBufD::new().join("../../a").dedup_adjacent(),
+            PathBufD::new().join("../../a")
+        );
+    }
+
+    #[test]
+    fn normalize_is_lexical() {
+        assert_eq!(
+            PathBufD::new().join("a/./b/../c").normalize(),
+            PathBufD::new().join("a/c")
+        );
+        assert_eq!(
+            PathBufD::new().join("/../a").normalize(),
+            PathBufD::new().join("/a")
+        );
+        assert_eq!(
+            PathBufD::new().join("../a/..").normalize(),
+            PathBufD::new().join("..")
+        );
+        assert_eq!(
+            PathBufD::new().join("a/..").normalize(),
+            PathBufD::new().join(".")
+        );
+    }
+
+    #[test]
+    fn subpath_and_parent_directions() {
+        let parent = PathBufD::new().join("/a/b");
+        let child = PathBufD::new().join("/a/b/c/../d");
+
+        assert!(child.is_subpath_of(&parent));
+        assert!(!child.is_parent_of(&parent));
+        assert!(parent.is_parent_of(&child));
+        assert!(!parent.is_subpath_of(&child));
+    }
+
+    #[test]
+    fn subpath_of_siblings_and_self() {
+        let a = PathBufD::new().join("/a/b");
+        let b = PathBufD::new().join("/a/bc");
+
+        assert!(!a.is_subpath_of(&b));
+        assert!(!a.is_parent_of(&b));
+
+        // a path is its own subpath and parent
+        assert!(a.is_subpath_of(&a));
+        assert!(a.is_parent_of(&a));
+
+        assert!(PathBufD::new().join("x/y").is_subpath_of("."));
+        assert!(!PathBufD::new().join("../y").is_subpath_of("."));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn null_device_unix() {
+        assert_eq!(PathBufD::null_device(), PathBufD::new().join("/dev/null"));
+        assert!(PathBufD::null_device().is_null_device());
+        assert!(!PathBufD::new().join("/dev/zero").is_null_device());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn null_device_windows() {
+        assert_eq!(PathBufD::null_device(), PathBufD::new().join("NUL"));
+
+        for path in ["NUL", "nul", "Nul", r"\\.\NUL", r"\\.\nul"] {
+            assert!(PathBufD::new().join(path).is_null_device(), "{path}");
+        }
+
+        assert!(!PathBufD::new().join("NULL").is_null_device());
+    }
+
+    #[test]
+    fn join_if_relative_keeps_absolute_argument() {
+        let base = PathBufD::new().join("/srv/data");
+        assert_eq!(
+            base.join_if_relative("user/file"),
+            PathBufD::new().join("/srv/data/user/file")
+        );
+        assert_eq!(
+            base.join_if_relative("/etc/passwd"),
+            PathBufD::new().join("/etc/passwd")
+        );
+    }
+
+    #[test]
+    fn try_join_no_escape_rejects_escapes() {
+        let base = PathBufD::new().join("/srv/data");
+        assert_eq!(
+            base.try_join_no_escape("user/./file"),
+            Ok(PathBufD::new().join("/srv/data/user/./file"))
+        );
+        assert_eq!(
+            base.try_join_no_escape("/etc/passwd"),
+            Err(PathError::EscapesBase)
+        );
+        assert_eq!(
+            base.try_join_no_escape("a/../../x"),
+            Err(PathError::EscapesBase)
+        );
+        assert_eq!(
+            PathBufD::new().try_join_no_escape("a/b"),
+            Ok(PathBufD::new().join("a/b"))
+        );
+    }
+
+    #[test]
+    fn verbose_display_annotations() {
+        let dir = temp_dir("verbose-display");
+        let file = dir.join("main.rs");
+        fs::write(&file, [b'x'; 842]).unwrap();
+
+        assert_eq!(
+            file.verbose_display().to_string(),
+            format!("{file} (file, 842 B)")
+        );
+        assert_eq!(
+            dir.verbose_display().to_string(),
+            format!("{} (directory)", *dir)
+        );
+
+        let missing = dir.join("missing/path");
+        assert_eq!(
+            missing.verbose_display().to_string(),
+            format!("{missing} (not found)")
+        );
+    }
+
+    #[test]
+    fn prefixes_are_cumulative() {
+        let p = |s: &str| PathBufD::new().join(s);
+
+        assert_eq!(p("/a/b/c").prefixes(), [p("/a"), p("/a/b"), p("/a/b/c")]);
+        assert_eq!(p("a/b").prefixes(), [p("a"), p("a/b")]);
+        assert_eq!(p("a").prefixes(), [p("a")]);
+        assert_eq!(p("/").prefixes(), [p("/")]);
+        assert!(p("").prefixes().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn canonicalize_partial_resolves_existing_prefix() {
+        let dir = temp_dir("canonicalize-partial");
+        let real = dir.join("real");
+        fs::create_dir(&real).unwrap();
+        std::os::unix::fs::symlink(&real, dir.join("link")).unwrap();
+
+        let resolved = dir
+            .join("link/new/file.txt")
+            .canonicalize_partial()
+            .unwrap();
+        let expected = real.as_path().canonicalize().unwrap().join("new/file.txt");
+        assert_eq!(resolved, PathBufD::from(expected));
+
+        assert_eq!(
+            real.canonicalize_partial().unwrap(),
+            PathBufD::from(real.as_path().canonicalize().unwrap())
+        );
+    }
+
+    #[test]
+    fn portable_bytes_round_trip() {
+        for path in ["", "a/b/c", "/usr/lib/файл"] {
+            let path = PathBufD::new().join(path);
+            assert_eq!(
+                PathBufD::from_portable_bytes(&path.to_portable_bytes()),
+                Ok(path)
+            );
+        }
+
+        assert_eq!(
+            PathBufD::from_portable_bytes(&[]),
+            Err(PathError::Malformed)
+        );
+        let mut truncated = PathBufD::new().join("abc").to_portable_bytes();
+        truncated.pop();
+        assert_eq!(
+            PathBufD::from_portable_bytes(&truncated),
+            Err(PathError::Malformed)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn portable_bytes_round_trip_non_utf8() {
+        let path = PathBufD::from(PathBuf::from(OsStr::from_bytes(b"dir/\xff\xfe")));
+        assert_eq!(
+            PathBufD::from_portable_bytes(&path.to_portable_bytes()),
+            Ok(path)
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn portable_bytes_round_trip_unpaired_surrogate() {
+        let path = PathBufD::from(PathBuf::from(OsString::from_wide(&[0x61, 0xd800])));
+        assert_eq!(
+            PathBufD::from_portable_bytes(&path.to_portable_bytes()),
+            Ok(path)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn portable_bytes_rejects_other_platforms() {
+        let mut windows = vec![1];
+        windows.extend_from_slice(&2u64.to_le_bytes());
+        windows.extend_from_slice(&[b'a', 0]);
+
+        assert_eq!(
+            PathBufD::from_portable_bytes(&windows),
+            Err(PathError::PlatformMismatch)
+        );
+    }
+
+    #[test]
+    fn with_root_rebases_paths() {
+        assert_eq!(
+            PathBufD::new().join("/etc/hosts").with_root("/sandbox"),
+            PathBufD::new().join("/sandbox/etc/hosts")
+        );
+        assert_eq!(
+            PathBufD::new().join("etc/hosts").with_root("/sandbox"),
+            PathBufD::new().join("/sandbox/etc/hosts")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn with_root_drops_drive_prefix() {
+        assert_eq!(
+            PathBufD::new()
+                .join(r"C:\Windows\notepad.exe")
+                .with_root(r"D:\sandbox"),
+            PathBufD::new().join(r"D:\sandbox\Windows\notepad.exe")
+        );
+    }
+
+    #[test]
+    fn diff_splits_common_prefix() {
+        let p = |s: &str| PathBufD::new().join(s);
+
+        assert_eq!(
+            p("/a/b/c/d").diff("/a/b/x/y"),
+            PathDiff {
+                common:  p("/a/b"),
+                removed: p("c/d"),
+                added:   p("x/y"),
+            }
+        );
+        assert_eq!(
+            p("a/b").diff("c/d"),
+            PathDiff {
+                common:  p(""),
+                removed: p("a/b"),
+                added:   p("c/d"),
+            }
+        );
+        assert_eq!(
+            p("/a/b").diff("/a/b"),
+            PathDiff {
+                common:  p("/a/b"),
+                removed: p(""),
+                added:   p(""),
+            }
+        );
+    }
+
+    #[test]
+    fn files_with_extension_matches_case_insensitively() {
+        let dir = temp_dir("files-with-extension");
+        for file in [
+            "b.md",
+            "a.MD",
+            "notes.txt",
+            "sub/c.md",
+            "sub/deeper/d.Md",
+            "sub/e.mdx",
+        ] {
+            dir.join(file).touch_all().unwrap();
+        }
+        fs::create_dir(dir.join("folder.md")).unwrap();
+
+        let found: Vec<PathBufD> = dir.files_with_extension(".md").unwrap();
+        let expected: Vec<PathBufD> = ["a.MD", "b.md", "sub/c.md", "sub/deeper/d.Md"]
+            .into_iter()
+            .map(|f| dir.join(f))
+            .collect();
+
+        assert_eq!(found, expected);
+        assert_eq!(dir.files_with_extension("md").unwrap(), expected);
+    }
+
+    #[test]
+    fn guessed_mime_from_extension() {
+        let mime = |s: &str| PathBufD::new().join(s).guessed_mime();
+
+        assert_eq!(mime("index.html"), Some("text/html"));
+        assert_eq!(mime("data.json"), Some("application/json"));
+        assert_eq!(mime("logo.png"), Some("image/png"));
+        assert_eq!(mime("INDEX.HTML"), Some("text/html"));
+        assert_eq!(mime("archive.unknownext"), None);
+        assert_eq!(mime("Makefile"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn make_executable_sets_execute_bits() {
+        let dir = temp_dir("make-executable");
+        let file = dir.join("run.sh");
+        file.touch().unwrap();
+        file.set_mode(0o640).unwrap();
+
+        file.make_executable().unwrap();
+        assert_eq!(
+            fs::metadata(&file).unwrap().permissions().mode() & 0o777,
+            0o751
+        );
+
+        file.set_mode(0o600).unwrap();
+        assert_eq!(
+            fs::metadata(&file).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+
+    #[test]
+    fn strip_extension_removes_final_extension() {
+        let mut path = PathBufD::new().join("dir/file.txt");
+        assert!(path.strip_extension());
+        assert_eq!(path, PathBufD::new().join("dir/file"));
+
+        assert!(!path.strip_extension());
+        assert_eq!(path, PathBufD::new().join("dir/file"));
+
+        assert_eq!(
+            PathBufD::new().join("archive.tar.gz").without_extension(),
+            PathBufD::new().join("archive.tar")
+        );
+    }
+
+    #[test]
+    fn components_below_ancestor() {
+        let path = PathBufD::new().join("/project/src/bin/x.rs");
+        let below: Vec<&OsStr> = path
+            .components_below("/project")
+            .unwrap()
+            .map(|c| c.as_os_str())
+            .collect();
+
+        assert_eq!(below, ["src", "bin", "x.rs"]);
+        assert!(path.components_below("/proj").is_none());
+        assert!(path.components_below("/other").is_none());
+    }
+
+    #[test]
+    fn encode_component_round_trips() {
+        for s in [
+            "a/b\\c d",
+            "naïve 日本/語",
+            "100%: \"why?\" <a|b> *",
+            "tab\there",
+            ".",
+            "..",
+            "",
+            "CON",
+            "nul.txt",
+            "com1",
+            "trailing.",
+            "%",
+            "%2F",
+        ] {
+            let encoded = encode_component(s);
+            let mut components = Path::new(&encoded).components();
+
+            assert!(
+                matches!(components.next(), Some(Component::Normal(_))),
+                "{s:?} -> {encoded:?}"
+            );
+            assert!(components.next().is_none(), "{s:?} -> {encoded:?}");
+            assert!(!encoded.ends_with('.'), "{s:?} -> {encoded:?}");
+            assert!(!is_reserved_windows_name(&encoded), "{s:?} -> {encoded:?}");
+            assert_eq!(decode_component(&encoded).as_deref(), Ok(s));
+        }
+    }
+
+    #[test]
+    fn encode_component_empty_and_reserved() {
+        assert_eq!(encode_component(""), "%");
+        assert_eq!(encode_component("CON"), "%43ON");
+        assert_eq!(encode_component("a/b"), "a%2Fb");
+        assert_eq!(decode_component("%4"), Err(PathError::Malformed));
+
+        let mut path = PathBufD::new().join("dir");
+        path.push_encoded("");
+        path.push_encoded("x/y");
+        assert_eq!(path, PathBufD::new().join("dir/%/x%2Fy"));
+    }
+
+    #[test]
+    fn eq_portable_ignores_separator_style() {
+        let path = PathBufD::new().join("a/b/c");
+        assert!(path.eq_portable(r"a\b\c"));
+        assert!(path.eq_portable("./a//b/c"));
+        assert!(PathBufD::new().join(r"a\b\c").eq_portable("a/b/c"));
+        assert!(!path.eq_portable(r"a\b"));
+        assert!(!path.eq_portable("/a/b/c"));
+    }
+
+    #[test]
+    fn open_create_and_options() {
+        use std::io::{Read, Write};
+
+        let dir = temp_dir("open-create");
+        let file = dir.join("data.txt");
+
+        file.create().unwrap().write_all(b"hello").unwrap();
+
+        let mut content = String::new();
+        file.open().unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "hello");
+
+        file.options()
+            .append(true)
+            .open()
+            .unwrap()
+            .write_all(b" world")
+            .unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "hello world");
+
+        assert!(file.options().write(true).create_new(true).open().is_err());
+    }
+
+    #[test]
+    fn debug_quotes_path() {
+        assert_eq!(
+            format!("{:?}", PathBufD::new().join("a/b")),
+            r#"PathBufD("a/b")"#
+        );
+        assert_eq!(
+            format!("{:?}", PathBufD::new().join(r#"say "hi""#)),
+            r#"PathBufD("say \"hi\"")"#
+        );
+        assert_eq!(
+            format!("{:#?}", PathBufD::new().join("a")),
+            r#"PathBufD("a")"#
+        );
+        assert_ne!(format!("{:?}", PathBufD::new().join("a")), "a");
+    }
+
+    #[test]
+    fn replace_prefix_remaps() {
+        let path = PathBufD::new().join("/build/tmp/out/app");
+        assert_eq!(
+            path.replace_prefix("/build/tmp", "/dist"),
+            Some(PathBufD::new().join("/dist/out/app"))
+        );
+        assert_eq!(
+            path.replace_prefix("/build/tmp/out/app", "/dist"),
+            Some(PathBufD::new().join("/dist"))
+        );
+        assert_eq!(path.replace_prefix("/build/tm", "/dist"), None);
+        assert_eq!(path.replace_prefix("/other", "/dist"), None);
+    }
+
+    #[test]
+    fn total_size_sums_files() {
+        let dir = temp_dir("total-size");
+        fs::write(dir.join("a"), [0; 100]).unwrap();
+        fs::create_dir_all(dir.join("sub/deeper")).unwrap();
+        fs::write(dir.join("sub/b"), [0; 20]).unwrap();
+        fs::write(dir.join("sub/deeper/c"), [0; 3]).unwrap();
+
+        assert_eq!(dir.total_size().unwrap(), 123);
+        assert_eq!(dir.join("sub/b").total_size().unwrap(), 20);
+        assert!(dir.join("missing").total_size().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn total_size_skips_symlinks() {
+        let dir = temp_dir("total-size-symlinks");
+        fs::write(dir.join("big"), [0; 1000]).unwrap();
+        fs::create_dir(dir.join("sub")).unwrap();
+        std::os::unix::fs::symlink(dir.join("big"), dir.join("sub/link")).unwrap();
+        std::os::unix::fs::symlink(dir.join("sub"), dir.join("dir-link")).unwrap();
+
+        assert_eq!(dir.total_size().unwrap(), 1000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn env_paths_round_trip_unix() {
+        let value = OsStr::new("/usr/local/bin:/usr/bin:/bin");
+        let paths = split_env_paths(value);
+
+        assert_eq!(
+            paths,
+            [
+                PathBufD::new().join("/usr/local/bin"),
+                PathBufD::new().join("/usr/bin"),
+                PathBufD::new().join("/bin"),
+            ]
+        );
+        assert_eq!(join_env_paths(&paths).unwrap(), value);
+        assert!(join_env_paths(&[PathBufD::new().join("a:b")]).is_err());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn env_paths_round_trip_windows() {
+        let value = OsStr::new(r"C:\Windows;C:\Program Files\App;D:\tools");
+        let paths = split_env_paths(value);
+
+        assert_eq!(
+            paths,
+            [
+                PathBufD::new().join(r"C:\Windows"),
+                PathBufD::new().join(r"C:\Program Files\App"),
+                PathBufD::new().join(r"D:\tools"),
+            ]
+        );
+        assert_eq!(join_env_paths(&paths).unwrap(), value);
+    }
+
+    #[test]
+    fn from_env_var_splits_value() {
+        let _guard = lock_cwd();
+        let name = "PATHBUFD_TEST_FROM_ENV_VAR";
+        let paths = [
+            PathBufD::new().join("first"),
+            PathBufD::new().join("second"),
+        ];
+
+        env::set_var(name, join_env_paths(&paths).unwrap());
+        assert_eq!(PathBufD::from_env_var(name), paths);
+
+        env::remove_var(name);
+        assert!(PathBufD::from_env_var(name).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn canonical_eq_follows_symlinks() {
+        let dir = temp_dir("canonical-eq");
+        fs::write(dir.join("a"), "a").unwrap();
+        fs::write(dir.join("b"), "b").unwrap();
+        std::os::unix::fs::symlink(dir.join("a"), dir.join("link")).unwrap();
+
+        assert!(dir.join("link").canonical_eq(dir.join("a")).unwrap());
+        assert!(dir.join("a").canonical_eq(dir.join("b/../a")).is_err());
+        assert!(dir.join("a").canonical_eq(dir.join(".").join("a")).unwrap());
+        assert!(!dir.join("a").canonical_eq(dir.join("b")).unwrap());
+        assert!(dir.join("a").canonical_eq(dir.join("missing")).is_err());
+    }
+
+    #[test]
+    fn with_numbered_suffix_before_extensions() {
+        let suffixed = |s: &str| PathBufD::new().join(s).with_numbered_suffix(3);
+
+        assert_eq!(
+            suffixed("dir/file.txt"),
+            PathBufD::new().join("dir/file (3).txt")
+        );
+        assert_eq!(
+            suffixed("file.tar.gz"),
+            PathBufD::new().join("file (3).tar.gz")
+        );
+        assert_eq!(suffixed("README"), PathBufD::new().join("README (3)"));
+        assert_eq!(suffixed(".env"), PathBufD::new().join(".env (3)"));
+    }
+
+    #[test]
+    fn next_available_skips_existing() {
+        let dir = temp_dir("next-available");
+        let file = dir.join("file.txt");
+        assert_eq!(file.next_available().unwrap(), file);
+
+        file.touch().unwrap();
+        dir.join("file (1).txt").touch().unwrap();
+        assert_eq!(file.next_available().unwrap(), dir.join("file (2).txt"));
+    }
+
+    #[cfg(feature = "glob")]
+    #[test]
+    fn glob_in_matches_patterns() {
+        let dir = temp_dir("glob-in");
+        for file in [
+            "main.rs",
+            "README.md",
+            "src/lib.rs",
+            "src/a/b.rs",
+            "src/a/c.txt",
+        ] {
+            dir.join(file).touch_all().unwrap();
+        }
+
+        let glob = |pattern: &str| -> Vec<PathBufD> {
+            PathBufD::glob_in(&dir, pattern)
+                .unwrap()
+                .into_iter()
+                .map(|p| PathBufD::from(p.as_path().strip_prefix(&dir).unwrap().to_path_buf()))
+                .collect()
+        };
+        let paths = |paths: &[&str]| -> Vec<PathBufD> {
+            paths.iter().map(|p| PathBufD::new().join(p)).collect()
+        };
+
+        assert_eq!(
+            glob("**/*.rs"),
+            paths(&["main.rs", "src/a/b.rs", "src/lib.rs"])
+        );
+        assert_eq!(glob("*.rs"), paths(&["main.rs"]));
+        assert_eq!(glob("src/*/?.*"), paths(&["src/a/b.rs", "src/a/c.txt"]));
+        assert_eq!(glob("[A-Z]*"), paths(&["README.md"]));
+        assert_eq!(
+            glob("src/**"),
+            paths(&["src", "src/a", "src/a/b.rs", "src/a/c.txt", "src/lib.rs"])
+        );
+        assert!(PathBufD::glob_in(dir.join("missing"), "*").is_err());
+    }
+
+    #[cfg(all(unix, feature = "glob"))]
+    #[test]
+    fn glob_in_skips_unreadable_directories() {
+        let dir = temp_dir("glob-in-unreadable");
+        dir.join("ok/a.rs").touch_all().unwrap();
+        dir.join("locked/b.rs").touch_all().unwrap();
+        dir.join("locked").set_mode(0o000).unwrap();
+
+        let found = PathBufD::glob_in(&dir, "**/*.rs");
+        dir.join("locked").set_mode(0o755).unwrap();
+
+        assert!(found.unwrap().contains(&dir.join("ok/a.rs")));
+    }
+
+    #[cfg(feature = "glob")]
+    #[test]
+    fn glob_is_relative_to_cwd() {
+        let _guard = lock_cwd();
+        let previous = env::current_dir().unwrap();
+        let dir = temp_dir("glob-cwd");
+        dir.join("src/lib.rs").touch_all().unwrap();
+        dir.join("build.rs").touch().unwrap();
+
+        env::set_current_dir(&dir).unwrap();
+        let found = PathBufD::glob("**/*.rs");
+        env::set_current_dir(previous).unwrap();
+
+        assert_eq!(
+            found.unwrap(),
+            [
+                PathBufD::new().join("build.rs"),
+                PathBufD::new().join("src/lib.rs"),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-width")]
+    fn display_width_counts_cells() {
+        let ascii = PathBufD::new().join("src/main.rs");
+        assert_eq!(ascii.display_width(), "src/main.rs".chars().count());
+
+        let wide = PathBufD::new().join("docs/日本語.md");
+        assert_eq!(wide.display_width(), "docs/".len() + 3 * 2 + ".md".len());
+
+        let full_width = PathBufD::new().join("ＡＢ");
+        assert_eq!(full_width.display_width(), 4);
+
+        let combining = PathBufD::new().join("e\u{301}");
+        assert_eq!(combining.display_width(), 1);
+
+        let family = PathBufD::new().join("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}");
+        assert_eq!(family.display_width(), 2);
+
+        let hebrew = PathBufD::new().join("\u{5E9}\u{5B8}");
+        assert_eq!(hebrew.display_width(), 1);
+    }
+
+    #[test]
+    fn push_str_matches_push() {
+        for base in ["", "a", "/", "/usr/", "dir/sub"] {
+            let mut pushed = PathBufD::new().join(base);
+            let mut pushed_str = pushed.clone();
+
+            for component in ["one", "two.txt", "日本", ".hidden"] {
+                pushed.push(component);
+                pushed_str.push_str(component);
+                assert_eq!(
+                    pushed_str.as_path().as_os_str(),
+                    pushed.as_path().as_os_str(),
+                    "{base:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn shares_root_with_absolute_and_relative() {
+        let absolute = PathBufD::new().join("/x");
+        assert!(absolute.shares_root_with("/z/y"));
+        assert!(!absolute.shares_root_with("z"));
+        assert!(PathBufD::new().join("a").shares_root_with("b/c"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn shares_root_with_drives() {
+        let c = PathBufD::new().join(r"C:\x");
+        assert!(!c.shares_root_with(r"D:\y"));
+        assert!(c.shares_root_with(r"C:\z"));
+        assert!(c.shares_root_with(r"c:\z"));
+        assert!(!c.shares_root_with(r"\\server\share\z"));
+    }
+
+    #[test]
+    fn format_tree_renders_connectors() {
+        let paths: Vec<PathBufD> = [
+            "project/Cargo.toml",
+            "project/src/lib.rs",
+            "project/src/main.rs",
+            "project/tests/it.rs",
+            "other",
+        ]
+        .into_iter()
+        .map(|p| PathBufD::new().join(p))
+        .collect();
+
+        assert_eq!(
+            format_tree(&paths),
+            concat!(
+                "project\n",
+                "├── Cargo.toml\n",
+                "├── src\n",
+                "│   ├── lib.rs\n",
+                "│   └── main.rs\n",
+                "└── tests\n",
+                "    └── it.rs\n",
+                "other\n",
+            )
+        );
+        assert_eq!(format_tree(&[]), "");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_uri_round_trip_unix() {
+        let path = PathBufD::new().join("/home/me/my docs/résumé #1.txt");
+        let uri = path.to_file_uri().unwrap();
+
+        assert_eq!(uri, "file:///home/me/my%20docs/r%C3%A9sum%C3%A9%20%231.txt");
+        assert_eq!(PathBufD::from_file_uri(&uri), Ok(path));
+        assert_eq!(
+            PathBufD::from_file_uri("file://localhost/etc/hosts"),
+            Ok(PathBufD::new().join("/etc/hosts"))
+        );
+
+        let non_utf8 = PathBufD::from(PathBuf::from(OsStr::from_bytes(b"/tmp/\xff")));
+        assert_eq!(
+            PathBufD::from_file_uri(&non_utf8.to_file_uri().unwrap()),
+            Ok(non_utf8)
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn file_uri_round_trip_windows() {
+        let path = PathBufD::new().join(r"C:\Users\me\my docs\résumé.txt");
+        let uri = path.to_file_uri().unwrap();
+
+        assert_eq!(uri, "file:///C:/Users/me/my%20docs/r%C3%A9sum%C3%A9.txt");
+        assert_eq!(PathBufD::from_file_uri(&uri), Ok(path));
+
+        let unc = PathBufD::new().join(r"\\server\share\a b.txt");
+        let uri = unc.to_file_uri().unwrap();
+
+        assert_eq!(uri, "file://server/share/a%20b.txt");
+        assert_eq!(PathBufD::from_file_uri(&uri), Ok(unc));
+    }
+
+    #[test]
+    fn file_uri_rejects_other_schemes() {
+        assert_eq!(
+            PathBufD::from_file_uri("http://example.com/a"),
+            Err(PathError::InvalidUri)
+        );
+        assert_eq!(PathBufD::from_file_uri("file:"), Err(PathError::InvalidUri));
+        assert_eq!(
+            PathBufD::from_file_uri("file:///a%zz"),
+            Err(PathError::Malformed)
+        );
+        assert_eq!(
+            PathBufD::new().join("relative").to_file_uri(),
+            Err(PathError::NotAbsolute)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "watch")]
+    fn watch_reports_created_files() {
+        let dir = temp_dir("watch");
+        let dir = PathBufD::from(dir.as_path().canonicalize().unwrap());
+        let watch = dir.watch().unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for path in watch {
+                if tx.send(path).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let file = dir.join("new.txt");
+        fs::write(&file, "x").unwrap();
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+
+        loop {
+            let timeout = deadline.saturating_duration_since(std::time::Instant::now());
+
+            if rx.recv_timeout(timeout).unwrap() == file {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "watch")]
+    fn watch_rejects_missing_directories() {
+        assert!(temp_dir("watch-missing").join("missing").watch().is_err());
+    }
+
+    #[test]
+    fn lexical_canonical_table() {
+        #[cfg(not(any(windows, target_os = "macos")))]
+        let table = [
+            ("a/./b/../c/", "a/c"),
+            ("/usr//Local/../bin", "/usr/bin"),
+            ("Dir/File.TXT", "Dir/File.TXT"),
+            ("a/..", "."),
+        ];
+
+        #[cfg(target_os = "macos")]
+        let table = [
+            ("a/./b/../c/", "a/c"),
+            ("/Users//Me/../Shared", "/users/shared"),
+            ("Dir/File.TXT", "dir/file.txt"),
+            ("a/..", "."),
+        ];
+
+        #[cfg(windows)]
+        let table = [
+            (r"a\.\b\..\c\", r"a\c"),
+            (r"\\?\C:\Users\Public", r"c:\users\public"),
+            (r"C:\Users\Me\..\Public", r"c:\users\public"),
+            (r"C:\Dir\File.TXT", r"c:\dir\file.txt"),
+            ("a/..", "."),
+        ];
+
+        for (input, expected) in table {
+            assert_eq!(
+                PathBufD::new().join(input).lexical_canonical(),
+                PathBufD::new().join(expected),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn set_component_replaces_by_index() {
+        let mut path = PathBufD::new().join("/a/b/c");
+        assert!(path.set_component(2, OsStr::new("x")));
+        assert_eq!(path, PathBufD::new().join("/a/x/c"));
+
+        assert!(path.set_component(3, OsStr::new("file.txt")));
+        assert_eq!(path, PathBufD::new().join("/a/x/file.txt"));
+
+        assert!(!path.set_component(4, OsStr::new("y")));
+        assert_eq!(path, PathBufD::new().join("/a/x/file.txt"));
+    }
+
+    #[test]
+    fn set_component_rejects_invalid_values() {
+        let mut path = PathBufD::new().join("/a/b/c");
+
+        for value in ["/z", "y/z", r"y\z", "..", ".", "", "nul\0"] {
+            assert!(!path.set_component(2, OsStr::new(value)), "{value:?}");
+        }
+
+        assert_eq!(path, PathBufD::new().join("/a/b/c"));
+    }
+
+    #[test]
+    fn file_name_and_extension_str() {
+        let path = PathBufD::new().join("dir/report.pdf");
+        assert_eq!(path.file_name_str(), Some("report.pdf"));
+        assert_eq!(path.extension_str(), Some("pdf"));
+
+        let bare = PathBufD::new().join("dir/README");
+        assert_eq!(bare.extension_str(), None);
+        assert_eq!(PathBufD::new().join("/").file_name_str(), None);
+        assert_eq!(PathBufD::new().join("a/..").file_name_str(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_name_and_extension_str_non_utf8() {
+        let path = PathBufD::from(PathBuf::from(OsStr::from_bytes(b"dir/\xff.t\xfft")));
+        assert!(path.as_path().file_name().is_some());
+        assert_eq!(path.file_name_str(), None);
+        assert_eq!(path.extension_str(), None);
+    }
+
+    #[test]
+    fn relative_to_or_self_falls_back() {
+        let path = PathBufD::new().join("/srv/app/logs/today.log");
+        assert_eq!(
+            path.relative_to_or_self("/srv/app"),
+            PathBufD::new().join("logs/today.log")
+        );
+        assert_eq!(path.relative_to_or_self("relative/base"), path);
+    }
+
+    #[test]
+    fn normalized_hash_matches_equivalent_forms() {
+        let hash = |s: &str| PathBufD::new().join(s).normalized_hash();
+
+        assert_eq!(hash("./a/b"), hash("a/b"));
+        assert_eq!(hash("a/b/"), hash("a/b"));
+        assert_eq!(hash("a/x/../b"), hash("a/b"));
+        assert_ne!(hash("a/b"), hash("a/c"));
+
+        assert!(PathBufD::new().join("./a/b").eq_normalized("a/b/"));
+        assert!(!PathBufD::new().join("a/b").eq_normalized("b/a"));
+    }
+
+    #[test]
+    fn normalized_path_as_map_key() {
+        let mut map = std::collections::HashMap::new();
+        map.insert(NormalizedPath::new("./a/b"), 1);
+        map.insert(NormalizedPath::new("a/b/"), 2);
+        map.insert(NormalizedPath::new("a/c"), 3);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&NormalizedPath::new("a/b")], 2);
+    }
+
+    #[test]
+    fn trailing_separator() {
+        let dir = PathBufD::new().join("dir/");
+        assert!(dir.ends_with_separator());
+        assert_eq!(dir.ensure_trailing_separator().to_string(), "dir/");
+
+        let bare = PathBufD::new().join("dir");
+        assert!(!bare.ends_with_separator());
+        assert!(bare.ensure_trailing_separator().ends_with_separator());
+        assert_eq!(
+            bare.ensure_trailing_separator().to_string(),
+            format!("dir{MAIN_SEPARATOR_STR}")
+        );
+
+        assert_eq!(PathBufD::new().ensure_trailing_separator(), PathBufD::new());
+    }
+
+    #[test]
+    fn pathd_safe_checks_arguments() {
+        let user_input = "../secret";
+        assert_eq!(
+            pathd_safe!("uploads/{}", user_input),
+            Err(PathError::InvalidSegment)
+        );
+        assert_eq!(
+            pathd_safe!("uploads/{}", "a/b"),
+            Err(PathError::InvalidSegment)
+        );
+
+        let user_input = "avatar.png";
+        assert_eq!(
+            pathd_safe!("uploads/{}/{}", 42, user_input),
+            Ok(PathBufD::new().join("uploads/42/avatar.png"))
+        );
+        assert_eq!(
+            pathd_safe!("static/{{literal}}/{0}", "x"),
+            Ok(PathBufD::new().join("static/{literal}/x"))
+        );
+    }
+
+    #[test]
+    fn positional_format_rejects_inline_captures() {
+        assert!(is_positional_format("uploads/{}/{0}/{:>4}/{1:?}"));
+        assert!(is_positional_format("escaped/{{name}}"));
+        assert!(!is_positional_format("uploads/{user_input}"));
+        assert!(!is_positional_format("{}/{name:?}"));
+    }
+
+    #[test]
+    fn read_dir_with_metadata_matches_stat() {
+        let dir = temp_dir("read-dir-metadata");
+        fs::write(dir.join("a.txt"), "hello").unwrap();
+        fs::create_dir(dir.join("sub")).unwrap();
+
+        let mut entries = dir.read_dir_with_metadata().unwrap();
+        entries.sort_by(|a, b| a.0.as_path().cmp(b.0.as_path()));
+        assert_eq!(entries.len(), 2);
+
+        for (path, metadata) in &entries {
+            let direct = fs::symlink_metadata(path).unwrap();
+            assert_eq!(metadata.is_dir(), direct.is_dir());
+            assert_eq!(metadata.is_file(), direct.is_file());
+            assert_eq!(metadata.modified().unwrap(), direct.modified().unwrap());
+
+            if metadata.is_file() {
+                assert_eq!(metadata.len(), direct.len());
+            }
+        }
+
+        assert_eq!(entries[0].0, dir.join("a.txt"));
+        assert_eq!(entries[0].1.len(), 5);
+        assert!(entries[1].1.is_dir());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn verbatim_is_identity() {
+        let path = PathBufD::new().join("/very/long/path");
+        assert_eq!(path.to_verbatim(), path);
+        assert_eq!(path.from_verbatim(), path);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn verbatim_drive_paths() {
+        let path = PathBufD::new().join(r"C:\very\long\path");
+        let verbatim = path.to_verbatim();
+
+        assert_eq!(verbatim.to_string(), r"\\?\C:\very\long\path");
+        assert_eq!(verbatim.from_verbatim(), path);
+        assert_eq!(verbatim.to_verbatim(), verbatim);
+
+        assert_eq!(
+            PathBufD::new()
+                .join(r"C:\a\.\b\..\c")
+                .to_verbatim()
+                .to_string(),
+            r"\\?\C:\a\c"
+        );
+        assert_eq!(
+            PathBufD::new().join(r"a\b").to_verbatim(),
+            PathBufD::new().join(r"a\b")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn verbatim_unc_shares() {
+        let path = PathBufD::new().join(r"\\server\share\dir\file.txt");
+        let verbatim = path.to_verbatim();
+
+        assert_eq!(verbatim.to_string(), r"\\?\UNC\server\share\dir\file.txt");
+        assert_eq!(
+            verbatim.from_verbatim().to_string(),
+            r"\\server\share\dir\file.txt"
+        );
+    }
+
+    #[test]
+    fn root_kind_classification() {
+        assert_eq!(PathBufD::new().join("/a").root_kind(), RootKind::Unix);
+        assert_eq!(PathBufD::new().join("a/b").root_kind(), RootKind::Relative);
+        assert_eq!(PathBufD::new().root_kind(), RootKind::None);
+
+        assert_eq!(
+            PathBufD::new().join("/a").first_component(),
+            Some(Component::RootDir)
+        );
+        assert_eq!(
+            PathBufD::new().join("a/b").first_component(),
+            Some(Component::Normal(OsStr::new("a")))
+        );
+        assert_eq!(PathBufD::new().first_component(), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn root_kind_windows_prefixes() {
+        assert_eq!(
+            PathBufD::new().join(r"C:\a").root_kind(),
+            RootKind::WindowsDrive('C')
+        );
+        assert_eq!(
+            PathBufD::new().join(r"d:\a").root_kind(),
+            RootKind::WindowsDrive('D')
+        );
+        assert_eq!(
+            PathBufD::new().join(r"\\?\C:\a").root_kind(),
+            RootKind::WindowsDrive('C')
+        );
+        assert_eq!(
+            PathBufD::new().join(r"\\server\share\a").root_kind(),
+            RootKind::Unc
+        );
+        assert_eq!(PathBufD::new().join(r"\a").root_kind(), RootKind::Unix);
+        assert!(matches!(
+            PathBufD::new().join(r"C:\a").first_component(),
+            Some(Component::Prefix(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn root_kind_windows_forms_on_unix() {
+        // without prefix parsing, these are plain relative names
+        assert_eq!(
+            PathBufD::new().join(r"C:\a").root_kind(),
+            RootKind::Relative
+        );
+        assert_eq!(
+            PathBufD::new().join(r"\\server\share\a").root_kind(),
+            RootKind::Relative
+        );
+    }
+
+    #[test]
+    fn join_with_capacity_reserves() {
+        let base = PathBufD::new().join("base");
+        let joined = base.join_with_capacity("child/file.txt", 256);
+
+        assert!(joined.capacity() >= 256);
+        assert_eq!(joined, base.join("child/file.txt"));
+
+        let joined = base.join_all_reserving(["a", "bb", "ccc.txt"]);
+        assert!(joined.capacity() >= "base/a/bb/ccc.txt".len());
+        assert_eq!(joined, PathBufD::new().join("base/a/bb/ccc.txt"));
+    }
+
+    static COUNTED_INITS: AtomicU64 = AtomicU64::new(0);
+
+    static_path! {
+        CONFIG = "config/app.toml";
+        CACHE = "cache";
+        COUNTED = {
+            COUNTED_INITS.fetch_add(1, AtomicOrdering::SeqCst);
+            "counted"
+        };
+    }
+
+    #[test]
+    fn static_path_initializes_once() {
+        assert_eq!(*CONFIG, PathBufD::new().join("config/app.toml"));
+        assert_eq!(CACHE.join("images"), PathBufD::new().join("cache/images"));
+
+        for _ in 0..3 {
+            assert_eq!(*COUNTED, PathBufD::new().join("counted"));
+        }
+        assert_eq!(COUNTED_INITS.load(AtomicOrdering::SeqCst), 1);
+
+        assert_eq!(PathBufD::from_static("a/b"), PathBufD::new().join("a/b"));
+    }
+
+    #[test]
+    fn after_and_until_component() {
+        let path = PathBufD::new().join("/home/u/projects/foo/src/lib.rs");
+        assert_eq!(
+            path.after_component("projects"),
+            Some(PathBufD::new().join("foo/src/lib.rs"))
+        );
+        assert_eq!(
+            path.until_component("projects"),
+            Some(PathBufD::new().join("/home/u/projects"))
+        );
+        assert_eq!(path.after_component("lib.rs"), Some(PathBufD::new()));
+        assert_eq!(path.after_component("missing"), None);
+        assert_eq!(path.until_component("missing"), None);
+    }
+
+    #[test]
+    fn after_and_until_component_use_first_match() {
+        let path = PathBufD::new().join("a/src/b/src/c");
+        assert_eq!(
+            path.after_component("src"),
+            Some(PathBufD::new().join("b/src/c"))
+        );
+        assert_eq!(
+            path.until_component("src"),
+            Some(PathBufD::new().join("a/src"))
+        );
+    }
+
+    #[test]
+    fn to_utf8_string_valid() {
+        assert_eq!(
+            PathBufD::new().join("dir/ok.txt").to_utf8_string(),
+            Ok("dir/ok.txt".to_string())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn to_utf8_string_reports_offset() {
+        let path = PathBufD::from(PathBuf::from(OsStr::from_bytes(b"ok/caf\xc3\xa9/\xffrest")));
+        let error = path.to_utf8_string().unwrap_err();
+
+        assert_eq!(error.valid_up_to(), 9);
+        assert!(error.to_string().contains('9'));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn to_utf8_string_reports_offset() {
+        let wide: Vec<u16> = "ok\\é".encode_utf16().chain([0xd800, 0x61]).collect();
+        let path = PathBufD::from(PathBuf::from(OsString::from_wide(&wide)));
+
+        assert_eq!(path.to_utf8_string().unwrap_err().valid_up_to(), 5);
+    }
+
+    #[test]
+    fn collect_and_extend_components() {
+        let path = PathBufD::new().join("/a/skip/b/skip/c");
+
+        let collected: PathBufD = path
+            .as_path()
+            .components()
+            .filter(|c| c.as_os_str() != "skip")
+            .collect();
+
+        let mut manual = PathBufD::new();
+        for part in ["/", "a", "b", "c"] {
+            manual.push(part);
+        }
+
+        assert_eq!(collected, manual);
+
+        let mut extended = PathBufD::new().join("base");
+        Extend::extend(&mut extended, Path::new("x/y").components());
+        assert_eq!(extended, PathBufD::new().join("base/x/y"));
+    }
+
+    #[test]
+    fn is_same_file_detects_hard_links() {
+        let dir = temp_dir("same-file");
+        fs::write(dir.join("original"), "data").unwrap();
+        fs::write(dir.join("copy"), "data").unwrap();
+        fs::hard_link(dir.join("original"), dir.join("hard")).unwrap();
+
+        let original = dir.join("original");
+        assert!(original.is_same_file(dir.join("hard")).unwrap());
+        assert!(!original.canonical_eq(dir.join("hard")).unwrap());
+        assert!(!original.is_same_file(dir.join("copy")).unwrap());
+        assert!(original.is_same_file(dir.join("missing")).is_err());
+    }
+
+    #[test]
+    fn into_string_utf8() {
+        let path = PathBufD::new().join("dir/file.txt");
+        assert_eq!(path.clone().into_string(), Ok(path.to_string()));
+        assert_eq!(path.into_string_lossy(), "dir/file.txt");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn into_string_returns_non_utf8_unchanged() {
+        let path = PathBufD::from(PathBuf::from(OsStr::from_bytes(b"dir/\xff")));
+        assert_eq!(path.clone().into_string(), Err(path.clone()));
+        assert_eq!(path.into_string_lossy(), "dir/\u{fffd}");
+    }
+
+    #[test]
+    fn dot_prefix() {
+        let p = |s: &str| PathBufD::new().join(s);
+
+        assert_eq!(p("foo").with_dot_prefix(), p("./foo"));
+        assert_eq!(p("./foo").with_dot_prefix(), p("./foo"));
+        assert_eq!(p("../foo").with_dot_prefix(), p("../foo"));
+        assert_eq!(p("/foo").with_dot_prefix(), p("/foo"));
+
+        assert_eq!(p("./foo").strip_dot_prefix(), p("foo"));
+        assert_eq!(p("foo").strip_dot_prefix(), p("foo"));
+        assert_eq!(p(".").strip_dot_prefix(), p("."));
+        assert_eq!(p("/foo").strip_dot_prefix(), p("/foo"));
+    }
+
+    #[test]
+    fn which_searches_path() {
+        let dir = temp_dir("which");
+
+        #[cfg(windows)]
+        let tool = dir.join("pathbufd-fake-tool.exe");
+        #[cfg(not(windows))]
+        let tool = dir.join("pathbufd-fake-tool");
+
+        tool.touch().unwrap();
+        tool.make_executable().unwrap();
+
+        let empty = temp_dir("which-empty");
+        let search_path = join_env_paths(&[empty.clone(), dir.clone()]).unwrap();
+
+        assert_eq!(
+            which_in("pathbufd-fake-tool", Some(&search_path)),
+            Some(tool.clone())
+        );
+        assert_eq!(
+            which_in(
+                "pathbufd-fake-tool",
+                Some(&join_env_paths(std::slice::from_ref(&*empty)).unwrap())
+            ),
+            None
+        );
+        assert_eq!(which_in("pathbufd-fake-tool", None), None);
+        assert_eq!(which_in(&tool.to_string(), None), Some(tool.clone()));
+        assert_eq!(which("pathbufd-definitely-missing-tool"), None);
+        assert_eq!(which(""), None);
+    }
+
+    #[test]
+    fn assert_within_passes_for_contained_paths() {
+        assert_within!("/srv/data/user/file", "/srv/data");
+        assert_within!(
+            PathBufD::new().join("/srv/data/a/../b"),
+            Path::new("/srv/data")
+        );
+        assert_within!("/srv/data/x", "/srv/data", "user {} upload", 42);
+    }
+
+    #[test]
+    #[should_panic(expected = "`/srv/data/../secret` is not within `/srv/data`")]
+    fn assert_within_panics_for_escaping_paths() {
+        assert_within!("/srv/data/../secret", "/srv/data");
+    }
+
+    #[test]
+    #[should_panic(expected = "is not within `/srv/data`: user 42")]
+    fn assert_within_includes_custom_message() {
+        assert_within!("/etc/passwd", "/srv/data", "user {}", 42);
+    }
+
+    #[test]
+    #[cfg(any(target_os = "macos", windows))]
+    fn canonical_case_fixes_casing() {
+        let dir = temp_dir("canonical-case");
+        fs::create_dir(dir.join("Documents")).unwrap();
+        fs::write(dir.join("Documents").join("Notes.txt"), "").unwrap();
+
+        let fixed = dir
+            .join("documents")
+            .join("NOTES.TXT")
+            .canonical_case()
+            .unwrap();
+        assert_eq!(fixed.as_path().file_name().unwrap(), "Notes.txt");
+        assert_eq!(
+            fixed.as_path().parent().unwrap().file_name().unwrap(),
+            "Documents"
+        );
+    }
+
+    #[test]
+    fn canonical_case_prefers_exact_matches() {
+        let dir = temp_dir("canonical-case-exact");
+        fs::create_dir(dir.join("Documents")).unwrap();
+
+        let path = dir.join("Documents");
+        assert_eq!(path.canonical_case().unwrap(), path);
+        assert_eq!(
+            dir.join("missing").canonical_case().unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn rename_children_renames_matching_children() {
+        let dir = temp_dir("rename-children");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+        fs::write(dir.join("c.md"), "c").unwrap();
+
+        let renamed = dir
+            .rename_children(|child| {
+                (child.as_path().extension()? == "txt")
+                    .then(|| PathBufD::from(child.as_path().with_extension("text")))
+            })
+            .unwrap();
+
+        assert_eq!(
+            renamed,
+            vec![
+                (dir.join("a.txt"), dir.join("a.text")),
+                (dir.join("b.txt"), dir.join("b.text")),
+            ]
+        );
+        assert!(dir.join("a.text").as_path().is_file());
+        assert!(dir.join("b.text").as_path().is_file());
+        assert!(dir.join("c.md").as_path().is_file());
+        assert!(!dir.join("a.txt").as_path().exists());
+    }
+
+    #[test]
+    fn rename_children_collision_renames_nothing() {
+        let dir = temp_dir("rename-children-collision");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+
+        let err = dir
+            .rename_children(|child| {
+                Some(PathBufD::from(child.as_path().with_file_name("same.txt")))
+            })
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(dir.join("a.txt").as_path().is_file());
+        assert!(dir.join("b.txt").as_path().is_file());
+        assert!(!dir.join("same.txt").as_path().exists());
+    }
+
+    #[test]
+    fn rename_children_rolls_back_on_failure() {
+        let dir = temp_dir("rename-children-rollback");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+
+        let err = dir
+            .rename_children(|child| {
+                if child.as_path().ends_with("a.txt") {
+                    Some(PathBufD::from(child.as_path().with_file_name("a.text")))
+                } else {
+                    Some(child.join("missing").join("b.text"))
+                }
+            })
+            .unwrap_err();
+
+        assert_ne!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(dir.join("a.txt").as_path().is_file());
+        assert!(!dir.join("a.text").as_path().exists());
+        assert!(dir.join("b.txt").as_path().is_file());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn rename_children_supports_swaps_and_chains() {
+        let dir = temp_dir("rename-children-swap");
+        fs::write(dir.join("a"), "a").unwrap();
+        fs::write(dir.join("b"), "b").unwrap();
+
+        let swap = |child: &PathBufD| {
+            let name = if child.as_path().ends_with("a") {
+                "b"
+            } else {
+                "a"
+            };
+            Some(PathBufD::from(child.as_path().with_file_name(name)))
+        };
+
+        assert_eq!(dir.rename_children(swap).unwrap().len(), 2);
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "b");
+        assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "a");
+
+        let next = |child: &PathBufD| {
+            let name = match child.file_name_str()? {
+                "a" => "b",
+                "b" => "c",
+                _ => return None,
+            };
+            Some(PathBufD::from(child.as_path().with_file_name(name)))
+        };
+
+        assert_eq!(dir.rename_children(next).unwrap().len(), 2);
+        assert!(!dir.join("a").as_path().exists());
+        assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "b");
+        assert_eq!(fs::read_to_string(dir.join("c")).unwrap(), "a");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn rename_children_rejects_existing_targets() {
+        let dir = temp_dir("rename-children-existing");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join("b.md"), "b").unwrap();
+
+        let err = dir
+            .rename_children(|child| {
+                (child.as_path().extension()? == "txt")
+                    .then(|| PathBufD::from(child.as_path().with_file_name("b.md")))
+            })
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.join("b.md")).unwrap(), "b");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn display_escaped_round_trips_invalid_bytes() {
+        let path = PathBufD::from(PathBuf::from(OsStr::from_bytes(
+            b"dir\\x/caf\xC3\xA9/\xFF\xFE\n",
+        )));
+        let escaped = path.display_escaped();
+
+        assert_eq!(escaped, r"dir\\x/café/\xFF\xFE\x0A");
+        assert_eq!(PathBufD::from_escaped(&escaped), Ok(path));
+    }
+
+    #[test]
+    fn from_escaped_rejects_malformed_escapes() {
+        for s in [r"\", r"\q", r"\x", r"\x4", r"\x+1", r"\x-1", r"\xG0"] {
+            assert_eq!(PathBufD::from_escaped(s), Err(PathError::Malformed), "{s}");
+        }
+
+        assert_eq!(
+            PathBufD::from_escaped(r"a\\b\x41"),
+            Ok(PathBufD::from(PathBuf::from(r"a\bA")))
+        );
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn display_escaped_round_trips_unpaired_surrogates() {
+        let path = PathBufD::from(PathBuf::from(OsString::from_wide(&[b'a' as u16, 0xD800])));
+        let escaped = path.display_escaped();
+
+        assert_eq!(escaped, r"a\u{D800}");
+        assert_eq!(PathBufD::from_escaped(&escaped), Ok(path));
+
+        for s in [r"\u{12", r"\u12}", r"\u{}", r"\u{+12}", r"\u{12345}"] {
+            assert_eq!(PathBufD::from_escaped(s), Err(PathError::Malformed), "{s}");
+        }
+    }
+
+    #[test]
+    fn parent_or_current_falls_back() {
+        assert_eq!(
+            PathBufD::new().join("a/b/c.txt").parent_or_current(),
+            PathBufD::new().join("a/b")
+        );
+        assert_eq!(
+            PathBufD::new().join("c.txt").parent_or_current(),
+            PathBufD::new().join(".")
+        );
+        assert_eq!(
+            PathBufD::new().parent_or_current(),
+            PathBufD::new().join(".")
+        );
+        assert_eq!(
+            PathBufD::new().join("/").parent_or_current(),
+            PathBufD::new().join("/")
+        );
+    }
+
+    #[test]
+    fn sharded_inserts_prefix_levels() {
+        let path = PathBufD::new().join("store/abcdef");
+
+        assert_eq!(
+            path.sharded(2, 2),
+            PathBufD::new().join("store/ab/cd/abcdef")
+        );
+        assert_eq!(path.sharded(1, 3), PathBufD::new().join("store/abc/abcdef"));
+        assert_eq!(
+            path.sharded(3, 1),
+            PathBufD::new().join("store/a/b/c/abcdef")
+        );
+        assert_eq!(path.sharded(0, 2), path);
+        assert_eq!(path.sharded(2, 0), path);
+    }
+
+    #[test]
+    fn sharded_clamps_short_names() {
+        assert_eq!(
+            PathBufD::new().join("abc").sharded(2, 2),
+            PathBufD::new().join("ab/abc")
+        );
+        assert_eq!(
+            PathBufD::new().join("a").sharded(2, 2),
+            PathBufD::new().join("a")
+        );
+    }
+
+    #[test]
+    fn freshness_compares_mtimes() {
+        let dir = temp_dir("freshness");
+        let source = dir.join("source.rs");
+        let output = dir.join("output.o");
+        let base = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        fs::File::create(&source)
+            .unwrap()
+            .set_modified(base + Duration::from_secs(60))
+            .unwrap();
+        fs::File::create(&output)
+            .unwrap()
+            .set_modified(base)
+            .unwrap();
+
+        assert!(source.is_newer_than(&output).unwrap());
+        assert!(!output.is_newer_than(&source).unwrap());
+        assert!(source.is_newer_than(dir.join("missing")).unwrap());
+        assert!(dir.join("missing").is_newer_than(&source).is_err());
+
+        assert!(source.modified_since(base).unwrap());
+        assert!(!output.modified_since(base).unwrap());
+        assert!(!source
+            .modified_since(base + Duration::from_secs(60))
+            .unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "relative-path")]
+    fn relative_path_conversions() {
+        use relative_path::RelativePathBuf;
+
+        let path = PathBufD::from(RelativePathBuf::from("assets/img/logo.png"));
+        assert_eq!(
+            path,
+            PathBufD::new().join("assets").join("img").join("logo.png")
+        );
+        assert_eq!(
+            path.to_relative(),
+            Some(RelativePathBuf::from("assets/img/logo.png"))
+        );
+
+        assert_eq!(
+            PathBufD::new().join("../a/./b").to_relative(),
+            Some(RelativePathBuf::from("../a/b"))
+        );
+        assert_eq!(PathBufD::new().join("/etc/hosts").to_relative(), None);
+
+        #[cfg(windows)]
+        assert_eq!(PathBufD::new().join(r"C:\Windows").to_relative(), None);
+    }
+
+    #[test]
+    fn ensure_extension_appends_only_when_missing() {
+        assert_eq!(
+            PathBufD::new().join("out/data.gz").ensure_extension("gz"),
+            PathBufD::new().join("out/data.gz")
+        );
+        assert_eq!(
+            PathBufD::new().join("data.GZ").ensure_extension(".gz"),
+            PathBufD::new().join("data.GZ")
+        );
+        assert_eq!(
+            PathBufD::new().join("data.csv").ensure_extension("gz"),
+            PathBufD::new().join("data.csv.gz")
+        );
+        assert_eq!(
+            PathBufD::new().join("data").ensure_extension(".gz"),
+            PathBufD::new().join("data.gz")
+        );
+        assert_eq!(
+            PathBufD::new().join("data").ensure_extension(""),
+            PathBufD::new().join("data")
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn resolve_link_once_follows_one_level() {
+        let dir = temp_dir("resolve-link-once");
+        let target = dir.join("target.txt");
+        fs::write(&target, "").unwrap();
+        std::os::unix::fs::symlink("target.txt", dir.join("middle")).unwrap();
+        std::os::unix::fs::symlink(dir.join("middle"), dir.join("outer")).unwrap();
+
+        assert_eq!(
+            dir.join("outer").resolve_link_once().unwrap(),
+            dir.join("middle")
+        );
+        assert_eq!(dir.join("middle").resolve_link_once().unwrap(), target);
+        assert_eq!(target.resolve_link_once().unwrap(), target);
+        assert!(dir.join("missing").resolve_link_once().is_err());
+    }
+
+    #[test]
+    fn truncate_file_name_bytes_respects_limits() {
+        let dir = PathBufD::new().join("dir");
+
+        assert_eq!(
+            dir.join("abcdefgh").truncate_file_name_bytes(5),
+            dir.join("abcde")
+        );
+        assert_eq!(dir.join("abc").truncate_file_name_bytes(5), dir.join("abc"));
+
+        // "é" is 2 bytes, so a cut at 3 bytes would split the second one
+        assert_eq!(dir.join("ééé").truncate_file_name_bytes(3), dir.join("é"));
+
+        assert_eq!(
+            dir.join("long-report-name.pdf")
+                .truncate_file_name_bytes(10),
+            dir.join("long-r.pdf")
+        );
+        assert_eq!(
+            dir.join("name.verylongext").truncate_file_name_bytes(6),
+            dir.join("name.v")
+        );
+    }
+
+    #[test]
+    fn truncate_file_name_bytes_keeps_names_that_cannot_fit() {
+        let path = PathBufD::new().join("dir/abc");
+        assert_eq!(path.truncate_file_name_bytes(0), path);
+
+        let path = PathBufD::new().join("dir/éa");
+        assert_eq!(path.truncate_file_name_bytes(1), path);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn is_executable_follows_execute_bit() {
+        let dir = temp_dir("is-executable");
+        let script = dir.join("run.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(!script.is_executable().unwrap());
+
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(script.is_executable().unwrap());
+
+        assert!(!dir.is_executable().unwrap());
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn is_executable_checks_extension() {
+        let dir = temp_dir("is-executable");
+        fs::write(dir.join("app.exe"), "").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        assert!(dir.join("app.exe").is_executable().unwrap());
+        assert!(!dir.join("notes.txt").is_executable().unwrap());
+        assert!(!dir.is_executable().unwrap());
+    }
+
+    #[test]
+    fn from_components_round_trips() {
+        let mut paths = vec!["/usr/local/bin", "a/b/c.txt", "../x/y", "./rel"];
+
+        if cfg!(windows) {
+            paths.extend([
+                r"C:\Users\me",
+                r"C:rel\file",
+                r"\\server\share\dir",
+                r"\\?\C:\long",
+            ]);
+        }
+
+        for path in paths {
+            let path = PathBufD::new().join(path);
+            let rebuilt = PathBufD::from_components(path.as_path().components());
+
+            assert_eq!(rebuilt.as_path().as_os_str(), path.as_path().as_os_str());
+        }
+
+        let path = PathBufD::new().join("/a/b/c");
+        assert_eq!(
+            PathBufD::from_components(path.as_path().components().skip(1)),
+            PathBufD::new().join("a/b/c")
+        );
+    }
+
+    #[test]
+    fn display_home_relative_collapses_home() {
+        let _guard = lock_cwd();
+        let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+        let previous = env::var_os(var);
+        let home = env::temp_dir().join("pathbufd-home");
+        env::set_var(var, &home);
+
+        let inside = PathBufD::from(home.join("projects").join("app"));
+        let outside = PathBufD::from(env::temp_dir().join("elsewhere"));
+
+        assert_eq!(
+            inside.display_home_relative(),
+            format!("~{MAIN_SEPARATOR_STR}projects{MAIN_SEPARATOR_STR}app")
+        );
+        assert_eq!(PathBufD::from(home.clone()).display_home_relative(), "~");
+        assert_eq!(outside.display_home_relative(), outside.to_string());
+
+        match previous {
+            Some(previous) => env::set_var(var, previous),
+            None => env::remove_var(var),
+        }
+    }
+
+    #[test]
+    fn with_retries_retries_transient_errors() {
+        let calls = std::cell::Cell::new(0);
+        let path = PathBufD::new().join("share/file");
+
+        let result = path.with_retries(5, |p| {
+            calls.set(calls.get() + 1);
+
+            if calls.get() <= 2 {
+                Err(io::Error::from(io::ErrorKind::Interrupted))
+            } else {
+                Ok(p.to_path_buf())
+            }
+        });
+
+        assert_eq!(result.unwrap(), path.as_path());
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn with_retries_returns_last_error() {
+        let calls = std::cell::Cell::new(0);
+        let path = PathBufD::new().join("share/file");
+
+        let err = path
+            .with_retries(3, |_| -> io::Result<()> {
+                calls.set(calls.get() + 1);
+                Err(io::Error::from(io::ErrorKind::WouldBlock))
+            })
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(calls.get(), 3);
+
+        calls.set(0);
+        let err = path
+            .with_retries(3, |_| -> io::Result<()> {
+                calls.set(calls.get() + 1);
+                Err(io::Error::from(io::ErrorKind::NotFound))
+            })
+            .unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn normalize_cow_borrows_clean_paths() {
+        for path in ["a/b/c", "/usr/lib", "../a", "."] {
+            let path = PathBufD::new().join(path);
+            assert!(
+                matches!(path.normalize_cow(), Cow::Borrowed(p) if *p == path),
+                "{path}"
+            );
+        }
+
+        for (path, expected) in [("a/./b", "a/b"), ("a/b/../c", "a/c"), ("a//b/", "a/b")] {
+            let path = PathBufD::new().join(path);
+
+            match path.normalize_cow() {
+                Cow::Owned(p) => {
+                    assert_eq!(p, path.normalize());
+                    assert_eq!(p, PathBufD::new().join(expected));
+                }
+                Cow::Borrowed(_) => panic!("{path} should be normalized"),
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn split_prefix_is_none_on_unix() {
+        let path = PathBufD::new().join("/a/b");
+        assert_eq!(path.split_prefix(), (None, path.clone()));
+
+        let path = PathBufD::new().join("a/b");
+        assert_eq!(path.split_prefix(), (None, path.clone()));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn split_prefix_splits_drives_and_shares() {
+        assert_eq!(
+            PathBufD::new().join(r"C:\a\b").split_prefix(),
+            (
+                Some(PathBufD::new().join("C:")),
+                PathBufD::new().join(r"\a\b")
+            )
+        );
+        assert_eq!(
+            PathBufD::new().join(r"\\srv\share\a").split_prefix(),
+            (
+                Some(PathBufD::new().join(r"\\srv\share")),
+                PathBufD::new().join(r"\a")
+            )
+        );
+        assert_eq!(
+            PathBufD::new().join(r"a\b").split_prefix(),
+            (None, PathBufD::new().join(r"a\b"))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "sha2")]
+    fn verify_sha256_checks_digest() {
+        let dir = temp_dir("verify-sha256");
+        let file = dir.join("download.bin");
+        fs::write(&file, "abc").unwrap();
+
+        let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert!(file.verify_sha256(digest).unwrap());
+        assert!(file.verify_sha256(&digest.to_uppercase()).unwrap());
+        assert!(!file
+            .verify_sha256("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+            .unwrap());
+        assert!(dir.join("missing").verify_sha256(digest).is_err());
+
+        // larger than one read buffer, to cover streaming
+        fs::write(&file, vec![b'a'; 1_000_000]).unwrap();
+        assert!(file
+            .verify_sha256("cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0")
+            .unwrap());
+    }
+
+    #[test]
+    fn join_sanitized_all_stays_under_base() {
+        let base = PathBufD::new().join("/srv/uploads");
+        let joined = base.join_sanitized_all(["..", "a/b", "..\\c", "report.pdf", "", "CON"]);
+
+        assert_eq!(
+            joined,
+            PathBufD::new().join("/srv/uploads/a_b/.._c/report.pdf/_CON")
+        );
+        assert!(joined.is_subpath_of(&base));
+        assert_eq!(base.join_sanitized_all(["..", ".", ""]), base);
+    }
+
+    #[test]
+    fn short_display_picks_shortest_form() {
+        let _guard = lock_cwd();
+        let previous_dir = env::current_dir().unwrap();
+        let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+        let previous_home = env::var_os(var);
+        let dir = temp_dir("short-display");
+        fs::create_dir(dir.join("work")).unwrap();
+
+        env::set_current_dir(dir.join("work")).unwrap();
+        let cwd = PathBufD::current();
+        let home = PathBufD::new()
+            .join(cwd.as_path().parent().unwrap())
+            .join("home");
+        env::set_var(var, home.as_path());
+
+        let in_cwd = cwd.join("src").join("main.rs").short_display();
+        let in_home = home.join("notes.txt").short_display();
+        let unrelated = PathBufD::new()
+            .join(cwd.as_path().parent().unwrap())
+            .join("elsewhere");
+        let unrelated_display = unrelated.short_display();
+
+        env::set_current_dir(previous_dir).unwrap();
+        match previous_home {
+            Some(previous) => env::set_var(var, previous),
+            None => env::remove_var(var),
+        }
+
+        assert_eq!(in_cwd, format!("src{MAIN_SEPARATOR_STR}main.rs"));
+        assert_eq!(in_home, format!("~{MAIN_SEPARATOR_STR}notes.txt"));
+        assert_eq!(unrelated_display, unrelated.to_string());
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn short_display_strips_verbatim_prefix() {
+        let path = PathBufD::new().join(r"\\?\Z:\data\file.txt");
+        assert_eq!(path.short_display(), r"Z:\data\file.txt");
+    }
+
+    #[test]
+    fn into_arc_and_rc_share_the_path() {
+        let path = PathBufD::new().join("/srv/shared/config.toml");
+
+        let arc = path.clone().into_arc();
+        let clone = Arc::clone(&arc);
+        assert_eq!(&*arc, path.as_path());
+        assert!(Arc::ptr_eq(&arc, &clone));
+        assert_eq!(
+            arc.as_os_str().as_encoded_bytes().as_ptr(),
+            clone.as_os_str().as_encoded_bytes().as_ptr()
+        );
+        assert_eq!(Arc::<Path>::from(path.clone()), arc);
+
+        let rc = path.clone().into_rc();
+        assert_eq!(&*rc, path.as_path());
+        assert!(Rc::ptr_eq(&rc, &Rc::clone(&rc)));
+        assert_eq!(Rc::<Path>::from(path), rc);
+    }
+
+    #[test]
+    fn component_strs_yields_every_component() {
+        let path = PathBufD::new().join("/a/b");
+
+        assert_eq!(
+            path.component_strs().collect::<Vec<_>>(),
+            [
+                OsStr::new(MAIN_SEPARATOR_STR),
+                OsStr::new("a"),
+                OsStr::new("b")
+            ]
+        );
+        assert!(path
+            .component_strs()
+            .eq(path.as_path().components().map(|c| c.as_os_str())));
+        assert!(matches!(
+            path.as_path().components().next(),
+            Some(Component::RootDir)
+        ));
+
+        assert_eq!(
+            PathBufD::new()
+                .join("../x")
+                .component_strs()
+                .collect::<Vec<_>>(),
+            [OsStr::new(".."), OsStr::new("x")]
+        );
+    }
+
+    #[test]
+    fn random_child_is_unique_under_self() {
+        let dir = PathBufD::new().join("/tmp/out");
+        let a = dir.random_child("render-", "png");
+        let b = dir.random_child("render-", "png");
+
+        assert_ne!(a, b);
+
+        for child in [&a, &b] {
+            assert_eq!(child.as_path().parent(), Some(dir.as_path()));
+
+            let name = child.file_name_str().unwrap();
+            assert!(name.starts_with("render-"), "{name}");
+            assert!(name.ends_with(".png"), "{name}");
+            assert_eq!(name.len(), "render-".len() + 16 + ".png".len());
+        }
+
+        let bare = dir.random_child("tmp", "");
+        assert_eq!(bare.extension_str(), None);
+        assert!(!bare.as_path().exists());
+    }
+
+    #[test]
+    fn is_empty_dir_checks_entries() {
+        let dir = temp_dir("is-empty-dir");
+        assert!(dir.is_empty_dir().unwrap());
+
+        fs::write(dir.join("file.txt"), "").unwrap();
+        assert!(!dir.is_empty_dir().unwrap());
+        assert!(!dir.join("file.txt").is_empty_dir().unwrap());
+        assert_eq!(
+            dir.join("missing").is_empty_dir().unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn prune_empty_dirs_removes_nested_empty_dirs() {
+        let dir = temp_dir("prune-empty");
+        fs::create_dir_all(dir.join("a/b/c")).unwrap();
+        fs::create_dir_all(dir.join("d")).unwrap();
+
+        assert_eq!(dir.prune_empty_dirs().unwrap(), 4);
+        assert!(dir.as_path().is_dir());
+        assert!(dir.is_empty_dir().unwrap());
+    }
+
+    #[test]
+    fn prune_empty_dirs_preserves_files() {
+        let dir = temp_dir("prune-keep");
+        fs::create_dir_all(dir.join("keep/empty")).unwrap();
+        fs::create_dir_all(dir.join("gone/empty")).unwrap();
+        fs::write(dir.join("keep/data.txt"), "data").unwrap();
+
+        assert_eq!(dir.prune_empty_dirs().unwrap(), 3);
+        assert!(dir.join("keep/data.txt").as_path().is_file());
+        assert!(!dir.join("keep/empty").as_path().exists());
+        assert!(!dir.join("gone").as_path().exists());
+    }
+
+    #[test]
+    fn eq_os_str_compares_exactly() {
+        let value = OsString::from("/opt/app/bin");
+
+        let path = PathBufD::new().join("/opt/app/bin");
+        assert_eq!(path, value);
+        assert_eq!(path, *value.as_os_str());
+        assert_ne!(path, OsString::from("/opt/app/bin/"));
+        assert_ne!(path, OsString::from("/opt//app/bin"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn eq_os_str_handles_non_utf8() {
+        let value = OsStr::from_bytes(b"/tmp/\xffdata").to_os_string();
+
+        assert_eq!(PathBufD::from(PathBuf::from(&value)), value);
+        assert_ne!(PathBufD::new().join("/tmp/\u{fffd}data"), value);
+    }
+
+    #[test]
+    fn file_stem_full_stops_at_first_dot() {
+        let path = PathBufD::new().join("dir/backup.tar.gz");
+        assert_eq!(path.file_stem_full(), Some(OsStr::new("backup")));
+        assert_eq!(path.as_path().file_stem(), Some(OsStr::new("backup.tar")));
+        assert_eq!(
+            path.strip_all_extensions(),
+            PathBufD::new().join("dir/backup")
+        );
+
+        let dotfile = PathBufD::new().join("dir/.env");
+        assert_eq!(dotfile.file_stem_full(), Some(OsStr::new(".env")));
+        assert_eq!(dotfile.strip_all_extensions(), dotfile);
+
+        let plain = PathBufD::new().join("dir/Makefile");
+        assert_eq!(plain.file_stem_full(), Some(OsStr::new("Makefile")));
+        assert_eq!(plain.strip_all_extensions(), plain);
+    }
+
+    #[test]
+    fn extensions_lists_every_segment() {
+        let extensions = |path: &str| {
+            PathBufD::new()
+                .join(path)
+                .extensions()
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(extensions("notes.txt"), ["txt"]);
+        assert_eq!(extensions("dir/backup.tar.gz"), ["tar", "gz"]);
+        assert_eq!(extensions("Makefile"), Vec::<String>::new());
+        assert_eq!(extensions(".gitignore"), Vec::<String>::new());
+        assert_eq!(extensions(".config.toml"), ["toml"]);
+    }
+
+    #[test]
+    fn relative_link_target_is_anchored_at_link_dir() {
+        let target = PathBufD::new().join("/srv/data/target.txt");
+
+        assert_eq!(
+            target.relative_link_target("/srv/links/nested/link"),
+            Some(PathBufD::new().join("../../data/target.txt"))
+        );
+        assert_eq!(
+            target.relative_link_target("/srv/data/link"),
+            Some(PathBufD::new().join("target.txt"))
+        );
+        assert_eq!(
+            PathBufD::new()
+                .join("/target")
+                .relative_link_target("/a/b/link"),
+            Some(PathBufD::new().join("../../target"))
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn relative_link_target_resolves_on_disk() {
+        let dir = temp_dir("relative-link-target");
+        let target = dir.join("data").join("target.txt");
+        let link = dir.join("links").join("nested").join("link");
+        target.touch_all().unwrap();
+        fs::create_dir_all(link.as_path().parent().unwrap()).unwrap();
+
+        let relative = target.relative_link_target(&link).unwrap();
+        std::os::unix::fs::symlink(&relative, &link).unwrap();
+
+        assert_eq!(relative, PathBufD::new().join("../../data/target.txt"));
+        assert!(link.as_path().is_file());
+    }
+
+    #[test]
+    fn abs_display_falls_back_gracefully() {
+        let _guard = lock_cwd();
+        let dir = temp_dir("abs-display");
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("real.txt"), "").unwrap();
+
+        let existing = dir.join("sub/../real.txt").abs_display();
+        assert_eq!(
+            existing,
+            dir.join("real.txt")
+                .as_path()
+                .canonicalize()
+                .unwrap()
+                .display()
+                .to_string()
+        );
+
+        let missing = PathBufD::new().join("missing/./file.txt").abs_display();
+        assert_eq!(
+            missing,
+            PathBufD::from(current_dir().unwrap())
+                .join("missing/file.txt")
+                .to_string()
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn abs_display_renders_non_utf8_paths() {
+        let path = PathBufD::from(PathBuf::from(OsStr::from_bytes(b"/nonexistent/\xff.txt")));
+        assert_eq!(path.abs_display(), "/nonexistent/\u{fffd}.txt");
+    }
+
+    #[test]
+    fn split_at_clamps_index() {
+        let path = PathBufD::new().join("/a/b/c/d");
+
+        assert_eq!(
+            path.split_at(2),
+            (PathBufD::new().join("/a/b"), PathBufD::new().join("c/d"))
+        );
+        assert_eq!(path.split_at(0), (PathBufD::new(), path.clone()));
+        assert_eq!(path.split_at(4), (path.clone(), PathBufD::new()));
+        assert_eq!(path.split_at(10), (path.clone(), PathBufD::new()));
+
+        assert_eq!(
+            PathBufD::new().join("x/y").split_at(1),
+            (PathBufD::new().join("x"), PathBufD::new().join("y"))
+        );
+    }
+
+    #[test]
+    fn stable_hash_matches_known_constants() {
+        assert_eq!(
+            PathBufD::new().join("a/b").stable_hash(),
+            0xab40d7820d408076
+        );
+        assert_eq!(
+            PathBufD::new().join("/usr/lib").stable_hash(),
+            0x10c726b4b7b44075
+        );
+    }
+
+    #[test]
+    fn stable_hash_equal_for_equal_normalized_paths() {
+        let hash = PathBufD::new().join("a/b").stable_hash();
+
+        assert_eq!(PathBufD::new().join("a/./b").stable_hash(), hash);
+        assert_eq!(PathBufD::new().join("a/c/../b/").stable_hash(), hash);
+        assert_ne!(PathBufD::new().join("a/c").stable_hash(), hash);
+        assert_ne!(PathBufD::new().join("ab").stable_hash(), hash);
+
+        #[cfg(windows)]
+        assert_eq!(PathBufD::new().join(r"a\b").stable_hash(), hash);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn stable_hash_distinguishes_non_utf8_paths() {
+        let a = PathBufD::from(PathBuf::from(OsStr::from_bytes(b"dir/\xff")));
+        let b = PathBufD::from(PathBuf::from(OsStr::from_bytes(b"dir/\xfe")));
+
+        assert_ne!(a.stable_hash(), b.stable_hash());
+        assert_eq!(a.stable_hash(), a.clone().stable_hash());
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn with_timestamp_at_uses_fixed_clock() {
+        let fmt = "%Y-%m-%dT%H-%M-%S";
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_717_243_200);
+
+        assert_eq!(
+            PathBufD::new()
+                .join("logs/app.log")
+                .with_timestamp_at(fmt, time),
+            Ok(PathBufD::new().join("logs/app.2024-06-01T12-00-00.log"))
+        );
+        assert_eq!(
+            PathBufD::new()
+                .join("logs/app")
+                .with_timestamp_at(fmt, time),
+            Ok(PathBufD::new().join("logs/app.2024-06-01T12-00-00"))
+        );
+
+        let leap = SystemTime::UNIX_EPOCH + Duration::from_secs(951_782_400 + 86_399);
+        assert_eq!(
+            PathBufD::new()
+                .join("a.log")
+                .with_timestamp_at("%Y%m%d-%H%M%S%%", leap),
+            Ok(PathBufD::new().join("a.20000229-235959%.log"))
+        );
+        assert_eq!(
+            PathBufD::new()
+                .join("a.log")
+                .with_timestamp_at("%j-%b", leap),
+            Ok(PathBufD::new().join("a.060-Feb.log"))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn with_timestamp_rejects_unsupported_specifiers() {
+        let path = PathBufD::new().join("app.log");
+
+        assert_eq!(path.with_timestamp("%Q"), Err(PathError::InvalidFormat));
+        assert_eq!(path.with_timestamp("%"), Err(PathError::InvalidFormat));
+        assert!(path.with_timestamp("%Y").is_ok());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn is_broken_symlink_detects_dangling_links() {
+        let dir = temp_dir("broken-symlink");
+        fs::write(dir.join("target.txt"), "").unwrap();
+        std::os::unix::fs::symlink("target.txt", dir.join("valid")).unwrap();
+        std::os::unix::fs::symlink("gone.txt", dir.join("dangling")).unwrap();
+
+        assert!(dir.join("dangling").is_broken_symlink().unwrap());
+        assert!(!dir.join("valid").is_broken_symlink().unwrap());
+        assert!(!dir.join("target.txt").is_broken_symlink().unwrap());
+        assert!(!dir.join("missing").is_broken_symlink().unwrap());
+    }
+
+    #[test]
+    fn renumber_keeps_number_and_extension() {
+        assert_eq!(
+            PathBufD::new()
+                .join("photos/IMG_0042.jpg")
+                .renumber("photo_"),
+            Some(PathBufD::new().join("photos/photo_0042.jpg"))
+        );
+        assert_eq!(
+            PathBufD::new().join("frame7").renumber("shot"),
+            Some(PathBufD::new().join("shot7"))
+        );
+        assert_eq!(
+            PathBufD::new().join("IMG_cover.jpg").renumber("photo_"),
+            None
+        );
+        assert_eq!(
+            PathBufD::new().join("0042.jpg").renumber("photo_"),
+            Some(PathBufD::new().join("photo_0042.jpg"))
+        );
+    }
+
+    #[test]
+    fn component_summary_counts_each_kind() {
+        assert_eq!(
+            PathBufD::new().join("./a/../b//c").component_summary(),
+            ComponentSummary {
+                normal:     3,
+                cur_dir:    1,
+                parent_dir: 1,
+                root:       0,
+                prefix:     0,
+            }
+        );
+        assert_eq!(
+            PathBufD::new().join("/a/b").component_summary(),
+            ComponentSummary {
+                normal: 2,
+                root: 1,
+                ..Default::default()
+            }
+        );
+
+        #[cfg(windows)]
+        assert_eq!(
+            PathBufD::new().join(r"C:\a").component_summary(),
+            ComponentSummary {
+                normal: 1,
+                root: 1,
+                prefix: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn join_utf8_joins_utf8_bases() {
+        let joined = PathBufD::new()
+            .join("/srv/données")
+            .join_utf8("fichier.txt")
+            .unwrap();
+
+        assert_eq!(joined, PathBufD::new().join("/srv/données/fichier.txt"));
+        assert!(joined.as_path().to_str().is_some());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn join_utf8_rejects_non_utf8_bases() {
+        let base = PathBufD::from(PathBuf::from(OsStr::from_bytes(b"/srv/\xff")));
+        assert_eq!(base.join_utf8("file.txt"), Err(PathError::NotUtf8));
+    }
+
+    #[test]
+    fn into_components_rebuilds_path() {
+        let path = PathBufD::new().join("/a/b/c");
+        let components: Vec<OsString> = path.clone().into_components().collect();
+
+        assert_eq!(components, [MAIN_SEPARATOR_STR, "a", "b", "c"]);
+        assert_eq!(components.iter().collect::<PathBuf>(), path.as_path());
+
+        let rebuilt = path
+            .clone()
+            .into_components()
+            .fold(PathBufD::new(), |acc, c| acc.join(c));
+        assert_eq!(rebuilt, path);
     }
 }